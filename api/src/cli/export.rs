@@ -0,0 +1,75 @@
+use crate::models::dataset::ExportFile;
+use crate::services::dataset::{DatasetService, write_export};
+use clap::{Args, ValueEnum};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ExportFormat {
+    /// `user_events.csv` and `event_keyword.csv` as `import.cypher` reads them, and `similar_users.csv`
+    Csv,
+    /// `graph.jsonl`, one node or relationship per line
+    Jsonl,
+    /// `graph.graphml`, e.g. for Gephi
+    Graphml,
+}
+
+impl ExportFormat {
+    fn files(&self) -> &'static [ExportFile] {
+        match self {
+            ExportFormat::Csv => &[ExportFile::UserEvents, ExportFile::EventKeywords, ExportFile::SimilarUsers],
+            ExportFormat::Jsonl => &[ExportFile::JsonLines],
+            ExportFormat::Graphml => &[ExportFile::GraphMl],
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Formats to write, can be repeated
+    #[arg(long, value_enum, default_value = "csv")]
+    pub format: Vec<ExportFormat>,
+
+    /// Directory the files are written to, created if missing
+    #[arg(long, default_value = ".")]
+    pub output: PathBuf,
+}
+
+/// Dumps the graph once and writes the files of every requested format from it.
+pub async fn run(args: ExportArgs, service: &DatasetService) -> ExitCode {
+    let dump = match service.dump().await {
+        Ok(dump) => dump,
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "Exporting {} users, {} events, {} keywords, {} registrations, {} event keywords and {} similarities",
+        dump.users.len(),
+        dump.events.len(),
+        dump.keywords.len(),
+        dump.registrations.len(),
+        dump.event_keywords.len(),
+        dump.similar.len()
+    );
+
+    if let Err(e) = fs::create_dir_all(&args.output) {
+        eprintln!("Can't create {}: {}", args.output.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    let mut formats = args.format;
+    formats.sort();
+    formats.dedup();
+    for file in formats.iter().flat_map(ExportFormat::files) {
+        let path = args.output.join(file.file_name());
+        if let Err(e) = File::create(&path).and_then(|out| write_export(&dump, *file, out)) {
+            eprintln!("Can't write {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        println!("Wrote {}", path.display());
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,589 @@
+use crate::models::dataset::{EventKeywordRow, EventRecord, ExportFile, GraphDump, RegistrationRecord, UserEventRow};
+use crate::models::event::EventId;
+use crate::models::registration::RsvpState;
+use crate::models::user::{Role, User};
+use crate::repo::RepoError;
+use crate::repo::dataset::DatasetRepository;
+use crate::services::dataset::write_export;
+use chrono::{Duration, NaiveDate, NaiveTime};
+use clap::{ArgGroup, Args};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("target").required(true).multiple(true)))]
+pub struct GenerateArgs {
+    /// Seed of the random generator, the same seed and options give the same dataset
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+
+    /// Number of users
+    #[arg(long, default_value_t = 10000)]
+    pub users: usize,
+
+    /// Number of events
+    #[arg(long, default_value_t = 250)]
+    pub events: usize,
+
+    /// Number of keywords, by default those of the built-in event categories
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub keywords: Option<u32>,
+
+    /// Share of the events that take place after the anchor date, from 0 to 1
+    #[arg(long, default_value_t = 0.5, value_parser = ratio)]
+    pub future_ratio: f64,
+
+    /// Date events are spread up to five years around
+    #[arg(long, default_value = DEFAULT_ANCHOR_DATE)]
+    pub anchor_date: NaiveDate,
+
+    /// Directory `user_events.csv` and `event_keyword.csv` are written to, created if missing
+    #[arg(long, group = "target")]
+    pub output: Option<PathBuf>,
+
+    /// Write the dataset to the graph
+    #[arg(long, group = "target")]
+    pub import: bool,
+
+    /// Delete everything but the schema migrations before writing to the graph, which has
+    /// to be empty otherwise
+    #[arg(long, requires = "import")]
+    pub replace: bool,
+
+    /// Rows written per transaction
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub batch_size: u32,
+}
+
+/// Fixed so the same options give the same dataset on any day.
+const DEFAULT_ANCHOR_DATE: &str = "2025-01-01";
+
+fn ratio(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("\"{}\" is not a number from 0 to 1", value)),
+    }
+}
+
+/// A kind of event, the events of `db_gen/events.py` condensed. Every event gets the
+/// category's keywords and those of its template, `{year}` is replaced by its year.
+struct Category {
+    keywords: &'static [&'static str],
+    templates: &'static [(&'static str, &'static [&'static str])],
+}
+
+const CATEGORIES: &[Category] = &[
+    Category {
+        keywords: &["rock_music", "live", "music"],
+        templates: &[
+            ("The Stone Roses Reunion", &[]),
+            ("Arctic Monkeys Live {year}", &[]),
+            ("Foo Fighters Concrete and Gold Tour", &[]),
+            ("Queens of the Stone Age Villains Tour", &[]),
+            ("Greta Van Fleet Starcatcher Tour", &[]),
+            ("Rock Fest {year}", &["festival"]),
+        ],
+    },
+    Category {
+        keywords: &["pop_music", "live", "music"],
+        templates: &[
+            ("Taylor Swift Eras Tour", &[]),
+            ("Dua Lipa Future Nostalgia Tour", &[]),
+            ("Harry Styles Love on Tour", &[]),
+            ("Billie Eilish World Tour {year}", &[]),
+            ("Olivia Rodrigo GUTS Tour", &[]),
+            ("The Weeknd After Hours Til Dawn Tour", &[]),
+        ],
+    },
+    Category {
+        keywords: &["electronic_music", "live", "festival", "music"],
+        templates: &[
+            ("Tomorrowland Belgium {year}", &[]),
+            ("EDC Las Vegas {year}", &[]),
+            ("Ultra Music Festival Miami {year}", &[]),
+            ("Awakenings Festival {year}", &["techno"]),
+            ("Creamfields UK {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["football", "match", "sport"],
+        templates: &[
+            ("Champions League Final {year}", &["competition"]),
+            ("Premier League Derby", &[]),
+            ("La Liga El Clasico", &[]),
+            ("Serie A Milan Derby", &[]),
+            ("Bundesliga Der Klassiker", &[]),
+            ("World Cup {year} Final", &["competition"]),
+        ],
+    },
+    Category {
+        keywords: &["basketball", "match", "competition", "sport"],
+        templates: &[
+            ("NBA Finals {year}", &[]),
+            ("EuroLeague Final Four {year}", &[]),
+            ("WNBA All-Star Game", &[]),
+            ("FIBA World Cup {year}", &[]),
+            ("NCAA Final Four {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["tennis", "match", "competition", "sport"],
+        templates: &[
+            ("Wimbledon Men's Final {year}", &[]),
+            ("French Open Women's Semi-final", &[]),
+            ("Australian Open {year}", &[]),
+            ("US Open Men's Final {year}", &[]),
+            ("ATP Finals {year}", &[]),
+            ("Davis Cup Final {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["volleyball", "match", "competition", "sport"],
+        templates: &[
+            ("FIVB World Championship Final", &[]),
+            ("NCAA Volleyball Championship {year}", &[]),
+            ("AVP Beach Volleyball Final", &[]),
+            ("CEV Champions League Final {year}", &[]),
+            ("FIVB Nations League Final", &[]),
+        ],
+    },
+    Category {
+        keywords: &["swimming", "competition", "sport"],
+        templates: &[
+            ("Olympic Swimming Finals {year}", &[]),
+            ("World Swimming Championships {year}", &[]),
+            ("European Swimming Championships {year}", &[]),
+            ("FINA World Cup {year}", &[]),
+            ("FINA Grand Prix {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["cycling", "competition", "sport"],
+        templates: &[
+            ("Tour de France Stage 10", &[]),
+            ("Giro d'Italia Stage 5", &[]),
+            ("Vuelta a España Stage 8", &[]),
+            ("UCI Road World Championships", &[]),
+            ("Paris-Roubaix {year}", &[]),
+            ("Tour of Flanders {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["hiking", "social", "outdoor", "sport"],
+        templates: &[
+            ("Guided Hike in the Tatra Mountains", &[]),
+            ("Guided Hike in the Bieszczady Mountains", &[]),
+            ("Guided Hike in the Pieniny Mountains", &[]),
+            ("Guided Hike in the Karkonosze Mountains", &[]),
+            ("Guided Hike in the Beskidy Mountains", &[]),
+        ],
+    },
+    Category {
+        keywords: &["running", "competition", "sport"],
+        templates: &[
+            ("Warsaw Marathon {year}", &["marathon"]),
+            ("Krakow Half Marathon {year}", &["half_marathon"]),
+            ("Gdynia 10K Run {year}", &["10k_run"]),
+            ("Poznan Marathon {year}", &["marathon"]),
+            ("Wroclaw Half Marathon {year}", &["half_marathon"]),
+            ("Lodz 10K Run {year}", &["10k_run"]),
+        ],
+    },
+    Category {
+        keywords: &["chess", "match", "competition"],
+        templates: &[
+            ("World Chess Championship {year}", &[]),
+            ("Candidates Tournament {year}", &[]),
+            ("Chess Olympiad {year}", &[]),
+            ("World Rapid Chess Championship {year}", &[]),
+            ("World Blitz Chess Championship {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["card_games", "tournament"],
+        templates: &[
+            ("World Series of Poker {year}", &["poker"]),
+            ("Magic: The Gathering Pro Tour {year}", &["tcg"]),
+            ("Yu-Gi-Oh! World Championship {year}", &["tcg"]),
+            ("Hearthstone World Championship {year}", &["online"]),
+            ("Gwent Masters {year}", &["online"]),
+        ],
+    },
+    Category {
+        keywords: &["dance"],
+        templates: &[
+            ("Beginner Hip Hop Class", &["hip_hop_dance", "class"]),
+            ("Intermediate Ballet Workshop", &["ballet", "workshop"]),
+            ("Salsa Social Dance Night", &["salsa", "social"]),
+            ("Contemporary Dance Performance", &["contemporary_dance", "live", "performance"]),
+            ("Ballroom Dance Competition", &["ballroom_dance", "competition"]),
+            ("Jazz Dance Workshop", &["jazz_dance", "workshop"]),
+        ],
+    },
+    Category {
+        keywords: &["yoga"],
+        templates: &[
+            ("Morning Vinyasa Flow", &["class"]),
+            ("Yoga for Beginners Workshop", &["workshop"]),
+            ("Yoga for Athletes Workshop", &["workshop"]),
+            ("Yoga for Stress Relief Workshop", &["workshop"]),
+        ],
+    },
+    Category {
+        keywords: &["technology", "conference"],
+        templates: &[
+            ("AI and Machine Learning Conference {year}", &[]),
+            ("Blockchain Technology Summit {year}", &[]),
+            ("Cybersecurity and Data Protection Conference {year}", &[]),
+            ("Cloud Computing and Virtualization Conference {year}", &[]),
+            ("Big Data and Analytics Conference {year}", &[]),
+        ],
+    },
+    Category {
+        keywords: &["photography", "social", "outdoor"],
+        templates: &[
+            ("Nature Photography Walk", &[]),
+            ("Urban Photography Walk", &[]),
+            ("Street Photography Walk", &[]),
+            ("Wildlife Photography Walk", &[]),
+        ],
+    },
+    Category {
+        keywords: &["cooking", "workshop"],
+        templates: &[
+            ("Italian Cuisine Cooking Class", &[]),
+            ("French Cuisine Cooking Class", &[]),
+            ("Japanese Cuisine Cooking Class", &[]),
+            ("Mexican Cuisine Cooking Class", &[]),
+            ("Indian Cuisine Cooking Class", &[]),
+        ],
+    },
+    Category {
+        keywords: &["writing", "workshop"],
+        templates: &[
+            ("Creative Writing Workshop", &[]),
+            ("Poetry Writing Workshop", &[]),
+            ("Fiction Writing Workshop", &[]),
+            ("Memoir Writing Workshop", &[]),
+            ("Screenwriting Workshop", &[]),
+        ],
+    },
+    Category {
+        keywords: &["science", "conference"],
+        templates: &[
+            ("Climate Change Conference {year}", &[]),
+            ("Genetics and Genomics Conference {year}", &[]),
+            ("Neuroscience Conference {year}", &[]),
+            ("Physics and Astronomy Conference {year}", &[]),
+            ("Chemistry Conference {year}", &[]),
+        ],
+    },
+];
+
+const FIRST_NAMES: &[&str] = &[
+    "adam", "alice", "amanda", "anna", "brian", "carla", "chris", "daniel", "david", "diana", "emily", "emma", "eric",
+    "grace", "hannah", "jacob", "james", "jason", "jennifer", "jessica", "john", "julia", "karen", "kevin", "kristen",
+    "laura", "linda", "lucas", "maria", "mark", "michael", "natalie", "nicole", "olivia", "paul", "peter", "rachel",
+    "robert", "sarah", "sophia", "steven", "thomas", "victoria", "william", "zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "adams", "allen", "baker", "brown", "campbell", "carter", "clark", "collins", "daugherty", "davis", "edwards",
+    "evans", "garcia", "green", "hall", "harris", "hill", "jackson", "johnson", "jones", "king", "lee", "lewis",
+    "martin", "miller", "mitchell", "moore", "nelson", "parker", "perez", "phillips", "roberts", "robinson", "scott",
+    "smith", "taylor", "thomas", "thompson", "turner", "walker", "white", "williams", "wilson", "wright", "young",
+];
+
+/// Events are spread over this many days before and after the anchor date.
+const DATE_SPREAD_DAYS: i64 = 5 * 365;
+
+/// A user registers to events of this many categories at most.
+const MAX_CATEGORIES_PER_USER: usize = 3;
+
+/// Picks per category of a user, with replacement, as `generator.py` does.
+const MAX_PAST_PICKS: usize = 10;
+const MAX_FUTURE_PICKS: usize = 3;
+
+/// Keywords in the order they first appear in [`CATEGORIES`].
+fn catalog_keywords() -> Vec<&'static str> {
+    let mut keywords = Vec::new();
+    for category in CATEGORIES {
+        let template_keywords = category.templates.iter().flat_map(|(_, keywords)| keywords.iter());
+        for keyword in category.keywords.iter().chain(template_keywords) {
+            if !keywords.contains(keyword) {
+                keywords.push(*keyword);
+            }
+        }
+    }
+    keywords
+}
+
+/// A faker-like user name, e.g. `kristendaugherty` or `qhill`, made unique with a number.
+fn user_name(rng: &mut ChaCha8Rng, taken: &mut HashSet<String>) -> String {
+    let first = FIRST_NAMES.choose(rng).expect("first names are not empty");
+    let last = LAST_NAMES.choose(rng).expect("last names are not empty");
+    let name = match rng.gen_range(0..4) {
+        0 => format!("{}{}", first, last),
+        1 => format!("{}{}", &first[..1], last),
+        2 => format!("{}{}", last, &first[..1]),
+        _ => format!("{}{}", first, rng.gen_range(10..100)),
+    };
+
+    let mut unique = name.clone();
+    let mut suffix = 1;
+    while taken.contains(&unique) {
+        suffix += 1;
+        unique = format!("{}{}", name, suffix);
+    }
+    taken.insert(unique.clone());
+    unique
+}
+
+/// Generates the dataset, every random choice is drawn from one generator seeded with `args.seed`.
+fn generate(args: &GenerateArgs, anchor: NaiveDate) -> GraphDump {
+    let mut rng = ChaCha8Rng::seed_from_u64(args.seed);
+
+    let mut keywords = catalog_keywords();
+    let keyword_count = args.keywords.map_or(keywords.len(), |count| count as usize);
+    keywords.truncate(keyword_count);
+    let extra_keywords: Vec<String> = (keywords.len()..keyword_count).map(|i| format!("topic_{}", i + 1)).collect();
+
+    let anchor = anchor.and_time(NaiveTime::MIN).and_utc();
+    let mut events = Vec::with_capacity(args.events);
+    let mut event_keywords = Vec::new();
+    // Past and upcoming event IDs per category, to pick registrations from.
+    let mut past: Vec<Vec<EventId>> = vec![Vec::new(); CATEGORIES.len()];
+    let mut future: Vec<Vec<EventId>> = vec![Vec::new(); CATEGORIES.len()];
+
+    for id in 1..=args.events as EventId {
+        let category_index = rng.gen_range(0..CATEGORIES.len());
+        let category = &CATEGORIES[category_index];
+        let (template, template_keywords) = category.templates.choose(&mut rng).expect("categories have templates");
+
+        let is_future = rng.gen_bool(args.future_ratio);
+        let days = rng.gen_range(1..=DATE_SPREAD_DAYS);
+        let start = if is_future { anchor + Duration::days(days) } else { anchor - Duration::days(days) }
+            + Duration::hours(rng.gen_range(1..24));
+        if is_future {
+            future[category_index].push(id);
+        } else {
+            past[category_index].push(id);
+        }
+
+        events.push(EventRecord {
+            id,
+            name: template.replace("{year}", &start.format("%Y").to_string()),
+            start_datetime: start.fixed_offset(),
+            end_datetime: None,
+            timezone: None,
+            capacity: None,
+            archived_at: None,
+        });
+
+        let mut added = BTreeSet::new();
+        for keyword in category.keywords.iter().chain(template_keywords.iter()) {
+            if keywords.contains(keyword) && added.insert(*keyword) {
+                event_keywords.push(EventKeywordRow {
+                    event_id: id,
+                    keyword: keyword.to_string(),
+                });
+            }
+        }
+        // `--keywords` may leave out all of the category's keywords, events need one.
+        if added.is_empty() {
+            let keyword = keywords.choose(&mut rng).expect("at least one keyword is kept");
+            event_keywords.push(EventKeywordRow {
+                event_id: id,
+                keyword: keyword.to_string(),
+            });
+        }
+    }
+
+    // Keywords beyond the catalog are attached to a few random events each.
+    if !events.is_empty() {
+        for keyword in &extra_keywords {
+            let mut tagged = BTreeSet::new();
+            for _ in 0..rng.gen_range(1..=3) {
+                tagged.insert(rng.gen_range(1..=args.events as EventId));
+            }
+            event_keywords.extend(tagged.into_iter().map(|event_id| EventKeywordRow {
+                event_id,
+                keyword: keyword.clone(),
+            }));
+        }
+    }
+    event_keywords.sort_by(|a, b| (a.event_id, &a.keyword).cmp(&(b.event_id, &b.keyword)));
+
+    let mut taken = HashSet::new();
+    let mut users = Vec::with_capacity(args.users);
+    let mut registrations = Vec::new();
+    let categories: Vec<usize> = (0..CATEGORIES.len())
+        .filter(|&i| !past[i].is_empty() || !future[i].is_empty())
+        .collect();
+
+    for _ in 0..args.users {
+        let name = user_name(&mut rng, &mut taken);
+        let mut registered = BTreeSet::new();
+
+        let category_count = rng.gen_range(1..=MAX_CATEGORIES_PER_USER).min(categories.len());
+        for &category in categories.choose_multiple(&mut rng, category_count) {
+            for (ids, max_picks) in [(&past[category], MAX_PAST_PICKS), (&future[category], MAX_FUTURE_PICKS)] {
+                if ids.is_empty() {
+                    continue;
+                }
+                for _ in 0..rng.gen_range(1..=ids.len().min(max_picks)) {
+                    registered.insert(*ids.choose(&mut rng).expect("ids are not empty"));
+                }
+            }
+        }
+
+        registrations.extend(registered.into_iter().map(|event_id| RegistrationRecord {
+            user_name: name.clone(),
+            event_id,
+            state: RsvpState::Going,
+            registered_at: None,
+        }));
+        users.push(User { name, role: Role::Attendee });
+    }
+
+    let mut keywords: Vec<String> = keywords.into_iter().map(str::to_string).chain(extra_keywords).collect();
+    keywords.sort();
+
+    GraphDump {
+        users,
+        events,
+        keywords,
+        registrations,
+        event_keywords,
+        similar: Vec::new(),
+    }
+}
+
+/// Writes events first, so events without registrations exist when their keywords are added.
+/// Generated IDs start at 1, so without `replace` the graph has to be empty or they would be
+/// merged onto existing events.
+async fn import(dump: &GraphDump, repo: &dyn DatasetRepository, replace: bool, batch_size: usize) -> Result<(), RepoError> {
+    if replace {
+        repo.clear().await?;
+    } else if !repo.is_empty().await? {
+        return Err(RepoError::Other("the graph is not empty, pass --replace to overwrite it".to_string()));
+    }
+    for rows in dump.events.chunks(batch_size) {
+        repo.import_events(rows).await?;
+    }
+
+    let events: HashMap<EventId, &EventRecord> = dump.events.iter().map(|event| (event.id, event)).collect();
+    for registrations in dump.registrations.chunks(batch_size) {
+        let rows: Vec<UserEventRow> = registrations
+            .iter()
+            .map(|registration| {
+                let event = events[&registration.event_id];
+                UserEventRow {
+                    user_name: registration.user_name.clone(),
+                    event_id: event.id,
+                    event_name: event.name.clone(),
+                    start_datetime: event.start_datetime,
+                }
+            })
+            .collect();
+        repo.import_user_events(&rows).await?;
+    }
+
+    for rows in dump.event_keywords.chunks(batch_size) {
+        repo.import_event_keywords(rows).await?;
+    }
+    repo.sync_event_id_counter().await
+}
+
+pub async fn run(args: GenerateArgs, repo: Option<&dyn DatasetRepository>) -> ExitCode {
+    let anchor = args.anchor_date;
+    let dump = generate(&args, anchor);
+    let upcoming = dump
+        .events
+        .iter()
+        .filter(|event| event.start_datetime.date_naive() > anchor)
+        .count();
+    println!(
+        "Generated {} users, {} events ({} upcoming), {} keywords and {} registrations around {}",
+        dump.users.len(),
+        dump.events.len(),
+        upcoming,
+        dump.keywords.len(),
+        dump.registrations.len(),
+        anchor
+    );
+
+    if let Some(output) = &args.output {
+        if let Err(e) = fs::create_dir_all(output) {
+            eprintln!("Can't create {}: {}", output.display(), e);
+            return ExitCode::FAILURE;
+        }
+        for file in [ExportFile::UserEvents, ExportFile::EventKeywords] {
+            let path = output.join(file.file_name());
+            if let Err(e) = File::create(&path).and_then(|out| write_export(&dump, file, out)) {
+                eprintln!("Can't write {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+            println!("Wrote {}", path.display());
+        }
+    }
+
+    if let Some(repo) = repo {
+        if let Err(e) = import(&dump, repo, args.replace, args.batch_size as usize).await {
+            eprintln!("Import failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+        println!("Wrote the dataset to the graph");
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        generate: GenerateArgs,
+    }
+
+    fn args(options: &[&str]) -> GenerateArgs {
+        let defaults = ["generate", "--users", "200", "--events", "60", "--output", "out"];
+        Cli::parse_from(defaults.iter().chain(options)).generate
+    }
+
+    /// Every node and relationship of the dataset, one JSON object per line.
+    fn json_lines(args: &GenerateArgs) -> String {
+        let mut content = Vec::new();
+        write_export(&generate(args, args.anchor_date), ExportFile::JsonLines, &mut content).unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_dataset() {
+        let args = args(&[]);
+        assert_eq!(args.anchor_date, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+
+        let dataset = json_lines(&args);
+        assert_eq!(dataset, json_lines(&args));
+        assert_ne!(dataset, json_lines(&self::args(&["--seed", "7"])));
+    }
+
+    #[test]
+    fn every_event_has_a_keyword() {
+        for options in [&[][..], &["--keywords", "1"], &["--keywords", "3"], &["--keywords", "200"]] {
+            let dump = generate(&args(options), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+            let tagged: BTreeSet<EventId> = dump.event_keywords.iter().map(|row| row.event_id).collect();
+            assert_eq!(tagged.len(), dump.events.len(), "{:?}", options);
+            assert!(dump.event_keywords.iter().all(|row| dump.keywords.contains(&row.keyword)));
+        }
+        assert!(Cli::try_parse_from(["generate", "--output", "out", "--keywords", "0"]).is_err());
+    }
+}
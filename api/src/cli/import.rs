@@ -0,0 +1,397 @@
+use crate::db::memory::MemoryConnection;
+use crate::models::dataset::{EVENT_KEYWORD_COLUMNS, EventKeywordRow, USER_EVENTS_COLUMNS, UserEventRow};
+use crate::repo::RepoError;
+use crate::repo::dataset::DatasetRepository;
+use crate::repo::memory::dataset::InMemoryDatasetRepository;
+use clap::{ArgGroup, Args};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use thiserror::Error;
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("files").required(true).multiple(true)))]
+pub struct ImportArgs {
+    /// CSV file with `user_name,event_id,event_name,start_datetime` rows
+    #[arg(long, group = "files")]
+    pub user_events: Option<PathBuf>,
+
+    /// CSV file with `event_id,keyword` rows, imported after the user events
+    #[arg(long, group = "files")]
+    pub event_keywords: Option<PathBuf>,
+
+    /// Rows written per transaction
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub batch_size: u32,
+
+    /// Delete everything but the schema migrations before importing
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Validate the files into an empty in-memory graph instead of the database
+    #[arg(long, conflicts_with = "replace")]
+    pub dry_run: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Can't read {0}: {1}")]
+    Read(String, csv::Error),
+
+    #[error("{0} has no {1} column")]
+    MissingColumn(String, String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] RepoError),
+}
+
+/// A row that was not imported and why.
+pub struct RejectedLine {
+    pub line: u64,
+    pub reason: String,
+}
+
+/// Row counts of one imported file.
+pub struct FileReport {
+    pub path: String,
+    pub rows: u64,
+    pub imported: u64,
+    pub rejected: Vec<RejectedLine>,
+}
+
+impl FileReport {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            rows: 0,
+            imported: 0,
+            rejected: Vec::new(),
+        }
+    }
+
+    fn reject(&mut self, line: u64, reason: String) {
+        self.rejected.push(RejectedLine { line, reason });
+    }
+}
+
+impl fmt::Display for FileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} rows, {} imported, {} rejected",
+            self.path,
+            self.rows,
+            self.imported,
+            self.rejected.len()
+        )?;
+        for rejected in &self.rejected {
+            writeln!(f, "  line {}: {}", rejected.line, rejected.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a CSV file a batch of validated rows at a time, with the line number of each row.
+/// Columns are matched by header name, so their order does not matter and extra ones are ignored.
+struct CsvBatches<R, T> {
+    path: String,
+    reader: csv::Reader<R>,
+    columns: Vec<usize>,
+    parse: fn(&[&str]) -> Result<T, String>,
+    batch_size: usize,
+}
+
+impl<T> CsvBatches<File, T> {
+    fn open(
+        path: &Path,
+        columns: &[&str],
+        parse: fn(&[&str]) -> Result<T, String>,
+        batch_size: usize,
+    ) -> Result<Self, ImportError> {
+        let path_name = path.display().to_string();
+        let file = File::open(path).map_err(|e| ImportError::Read(path_name.clone(), e.into()))?;
+        Self::new(path_name, file, columns, parse, batch_size)
+    }
+}
+
+impl<R: io::Read, T> CsvBatches<R, T> {
+    /// Reads `input`, named `path_name` in reports and errors.
+    fn new(
+        path_name: String,
+        input: R,
+        columns: &[&str],
+        parse: fn(&[&str]) -> Result<T, String>,
+        batch_size: usize,
+    ) -> Result<Self, ImportError> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+
+        let headers = reader
+            .headers()
+            .map_err(|e| ImportError::Read(path_name.clone(), e))?
+            .clone();
+        let columns = columns
+            .iter()
+            .map(|column| {
+                headers
+                    .iter()
+                    .position(|header| header.trim() == *column)
+                    .ok_or_else(|| ImportError::MissingColumn(path_name.clone(), column.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            path: path_name,
+            reader,
+            columns,
+            parse,
+            batch_size,
+        })
+    }
+
+    /// The next batch of valid rows, empty at the end of the file. Invalid rows are
+    /// counted and recorded in `report`.
+    fn next_batch(&mut self, report: &mut FileReport) -> Result<Vec<(u64, T)>, ImportError> {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut record = csv::StringRecord::new();
+
+        while batch.len() < self.batch_size {
+            match self.reader.read_record(&mut record) {
+                Ok(false) => break,
+                Ok(true) => {
+                    report.rows += 1;
+                    let line = record.position().map_or(0, |position| position.line());
+                    let values: Option<Vec<&str>> = self.columns.iter().map(|&i| record.get(i)).collect();
+                    let row = match values {
+                        Some(values) => (self.parse)(&values),
+                        None => Err(format!("Expected {} columns, found {}", self.columns.len(), record.len())),
+                    };
+                    match row {
+                        Ok(row) => batch.push((line, row)),
+                        Err(reason) => report.reject(line, reason),
+                    }
+                }
+                Err(e) if e.is_io_error() => return Err(ImportError::Read(self.path.clone(), e)),
+                Err(e) => {
+                    report.rows += 1;
+                    let line = e.position().map_or(0, |position| position.line());
+                    report.reject(line, e.to_string());
+                }
+            }
+        }
+        Ok(batch)
+    }
+}
+
+async fn import_user_events<R: io::Read>(
+    repo: &dyn DatasetRepository,
+    mut batches: CsvBatches<R, UserEventRow>,
+    report: &mut FileReport,
+) -> Result<(), ImportError> {
+    loop {
+        let batch = batches.next_batch(report)?;
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let rows: Vec<UserEventRow> = batch.into_iter().map(|(_, row)| row).collect();
+        repo.import_user_events(&rows).await?;
+        report.imported += rows.len() as u64;
+    }
+}
+
+async fn import_event_keywords<R: io::Read>(
+    repo: &dyn DatasetRepository,
+    mut batches: CsvBatches<R, EventKeywordRow>,
+    report: &mut FileReport,
+) -> Result<(), ImportError> {
+    loop {
+        let batch = batches.next_batch(report)?;
+        if batch.is_empty() {
+            report.rejected.sort_by_key(|rejected| rejected.line);
+            return Ok(());
+        }
+
+        let (lines, rows): (Vec<u64>, Vec<EventKeywordRow>) = batch.into_iter().unzip();
+        let unknown = repo.import_event_keywords(&rows).await?;
+        for (line, row) in lines.into_iter().zip(rows) {
+            if unknown.contains(&row.event_id) {
+                report.reject(line, format!("Event {} does not exist", row.event_id));
+            } else {
+                report.imported += 1;
+            }
+        }
+    }
+}
+
+/// Imports user events first so keywords can refer to their events, adding a report per
+/// file to `reports`. Each batch is written in its own transaction, batches written
+/// before an error stay in the graph.
+async fn import<R: io::Read>(
+    repo: &dyn DatasetRepository,
+    user_events: Option<CsvBatches<R, UserEventRow>>,
+    event_keywords: Option<CsvBatches<R, EventKeywordRow>>,
+    replace: bool,
+    reports: &mut Vec<FileReport>,
+) -> Result<(), ImportError> {
+    if replace {
+        repo.clear().await?;
+    }
+    if let Some(batches) = user_events {
+        reports.push(FileReport::new(&batches.path));
+        let report = reports.last_mut().expect("report was just added");
+        import_user_events(repo, batches, report).await?;
+        repo.sync_event_id_counter().await?;
+    }
+    if let Some(batches) = event_keywords {
+        reports.push(FileReport::new(&batches.path));
+        let report = reports.last_mut().expect("report was just added");
+        import_event_keywords(repo, batches, report).await?;
+    }
+    Ok(())
+}
+
+/// Imports the files given in `args` into `repo` and prints a report. Dry runs validate
+/// into an empty in-memory graph instead, `repo` is left untouched and may be `None`.
+pub async fn run(args: ImportArgs, repo: Option<&dyn DatasetRepository>) -> ExitCode {
+    let batch_size = args.batch_size as usize;
+    let scratch = InMemoryDatasetRepository::new(MemoryConnection::new().graph);
+    let repo = match repo {
+        Some(repo) if !args.dry_run => repo,
+        _ => &scratch,
+    };
+    let mut reports = Vec::new();
+
+    let result = async {
+        // Both files are opened, and their headers checked, before anything is written
+        let user_events = args
+            .user_events
+            .as_deref()
+            .map(|path| CsvBatches::open(path, USER_EVENTS_COLUMNS, UserEventRow::parse, batch_size))
+            .transpose()?;
+        let event_keywords = args
+            .event_keywords
+            .as_deref()
+            .map(|path| CsvBatches::open(path, EVENT_KEYWORD_COLUMNS, EventKeywordRow::parse, batch_size))
+            .transpose()?;
+        import(repo, user_events, event_keywords, args.replace, &mut reports).await
+    }
+    .await;
+
+    if args.dry_run {
+        println!("Dry run, nothing was written to the database");
+    }
+    for report in &reports {
+        print!("{}", report);
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Import failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn user_events(csv: &str, batch_size: usize) -> CsvBatches<&[u8], UserEventRow> {
+        CsvBatches::new("user_events.csv".to_string(), csv.as_bytes(), USER_EVENTS_COLUMNS, UserEventRow::parse, batch_size)
+            .unwrap()
+    }
+
+    fn event_keywords(csv: &str) -> CsvBatches<&[u8], EventKeywordRow> {
+        CsvBatches::new("event_keyword.csv".to_string(), csv.as_bytes(), EVENT_KEYWORD_COLUMNS, EventKeywordRow::parse, 10)
+            .unwrap()
+    }
+
+    #[test]
+    fn invalid_rows_are_rejected_with_their_line() {
+        let mut batches = user_events(
+            "\
+start_datetime,user_name,event_id,event_name
+2099-01-01T10:00:00Z,alice,1,Concert
+2099-01-01T10:00:00Z,bob
+tomorrow,carol,1,Concert
+2099-01-01T10:00:00Z,dave,x,Concert
+2099-01-01,erin,2,Match
+",
+            1,
+        );
+        let mut report = FileReport::new("user_events.csv");
+
+        let first = batches.next_batch(&mut report).unwrap();
+        assert_eq!(first.iter().map(|(line, row)| (*line, row.user_name.as_str())).collect::<Vec<_>>(), [(2, "alice")]);
+        let second = batches.next_batch(&mut report).unwrap();
+        assert_eq!(second.iter().map(|(line, _)| *line).collect::<Vec<_>>(), [6]);
+        assert!(batches.next_batch(&mut report).unwrap().is_empty());
+
+        assert_eq!(report.rows, 5);
+        assert_eq!(report.rejected.iter().map(|rejected| rejected.line).collect::<Vec<_>>(), [3, 4, 5]);
+        assert!(report.rejected[0].reason.starts_with("Expected 4 columns"));
+    }
+
+    #[test]
+    fn missing_columns_are_refused_up_front() {
+        let result = CsvBatches::new(
+            "user_events.csv".to_string(),
+            "user_name,event_id\nalice,1\n".as_bytes(),
+            USER_EVENTS_COLUMNS,
+            UserEventRow::parse,
+            10,
+        );
+        assert!(matches!(result, Err(ImportError::MissingColumn(_, column)) if column == "event_name"));
+    }
+
+    #[rocket::async_test]
+    async fn import_syncs_the_event_id_counter_and_rejects_unknown_events() {
+        let graph = MemoryConnection::new().graph;
+        let repo = InMemoryDatasetRepository::new(graph.clone());
+        let mut reports = Vec::new();
+
+        import(
+            &repo,
+            Some(user_events(
+                "user_name,event_id,event_name,start_datetime\nalice,9,Concert,2099-01-01\nbob,5,Match,2099-01-02\n",
+                1,
+            )),
+            Some(event_keywords("event_id,keyword\n9,music\n7,sport\n")),
+            false,
+            &mut reports,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(graph.read().event_id_counter, 9);
+        assert_eq!((reports[0].rows, reports[0].imported), (2, 2));
+        assert_eq!((reports[1].rows, reports[1].imported), (2, 1));
+        assert_eq!(reports[1].rejected[0].reason, "Event 7 does not exist");
+    }
+
+    #[rocket::async_test]
+    async fn dry_runs_write_nothing() {
+        let dir = std::env::temp_dir().join(format!("import-dry-run-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("user_events.csv");
+        fs::write(&path, "user_name,event_id,event_name,start_datetime\nalice,1,Concert,2099-01-01\n").unwrap();
+
+        let repo = InMemoryDatasetRepository::new(MemoryConnection::new().graph);
+        let args = ImportArgs {
+            user_events: Some(path),
+            event_keywords: None,
+            batch_size: 10,
+            replace: false,
+            dry_run: true,
+        };
+        let code = run(args, Some(&repo)).await;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(repo.is_empty().await.unwrap());
+    }
+}
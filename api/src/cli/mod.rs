@@ -0,0 +1,23 @@
+use clap::{Parser, Subcommand};
+
+pub mod export;
+pub mod generate;
+pub mod import;
+
+/// Event recommendation API. Serves the API when no command is given.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Import `user_events.csv` and `event_keyword.csv` files into the graph
+    Import(import::ImportArgs),
+    /// Export users, events, keywords and their relationships as CSV, JSON Lines or GraphML
+    Export(export::ExportArgs),
+    /// Generate a reproducible `user_events.csv` and `event_keyword.csv` dataset
+    Generate(generate::GenerateArgs),
+}
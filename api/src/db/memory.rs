@@ -0,0 +1,136 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::models::event::{Event, EventId};
+use crate::models::registration::RsvpState;
+use crate::models::revision::Revision;
+use crate::models::user::{Role, User};
+
+pub struct EventNode {
+    pub name: String,
+    pub start_datetime: DateTime<FixedOffset>,
+    pub end_datetime: Option<DateTime<FixedOffset>>,
+    pub timezone: Option<String>,
+    pub capacity: Option<i64>,
+    pub keywords: Vec<String>,
+    pub featured_until: Option<DateTime<FixedOffset>>,
+    /// Set while the event is archived.
+    pub archived_at: Option<DateTime<FixedOffset>>,
+    /// Bumped on every edit, see `Event::version`.
+    pub version: i64,
+    /// Last handed out waitlist position, positions are never reused.
+    pub waitlist_counter: i64,
+}
+
+pub struct RegistrationEdge {
+    pub state: RsvpState,
+    pub registered_at: DateTime<FixedOffset>,
+}
+
+impl RegistrationEdge {
+    pub fn new(state: RsvpState) -> Self {
+        Self {
+            state,
+            registered_at: Utc::now().fixed_offset(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UserNode {
+    pub password_hash: Option<String>,
+    /// See `UserRepository::find_token_version`.
+    pub token_version: i64,
+    pub role: Role,
+}
+
+impl UserNode {
+    pub fn to_user(&self, name: &str) -> User {
+        User {
+            name: name.to_string(),
+            role: self.role,
+        }
+    }
+}
+
+/// Plain in-process mirror of the Neo4j graph: nodes are keyed by their unique property
+/// and relationships are stored as `(from, to)` pairs.
+#[derive(Default)]
+pub struct GraphData {
+    pub users: BTreeMap<String, UserNode>,
+    pub events: BTreeMap<EventId, EventNode>,
+    /// Last allocated event ID, so IDs of deleted events are not handed out again.
+    pub event_id_counter: EventId,
+    pub keywords: BTreeSet<String>,
+    pub registrations: BTreeMap<(String, EventId), RegistrationEdge>,
+    /// `WAITLISTED` edges, the value is the `position` property.
+    pub waitlist: BTreeMap<(String, EventId), i64>,
+    /// `ORGANIZES` edges, the value is the `primary` flag.
+    pub organizes: BTreeMap<(String, EventId), bool>,
+    /// `EventRevision` nodes of each event, oldest first.
+    pub revisions: BTreeMap<EventId, Vec<Revision>>,
+    /// `SIMILAR` edges, normally written by the GDS pipeline in `frp.cypher`.
+    pub similar: BTreeSet<(String, String)>,
+}
+
+impl GraphData {
+    pub fn to_event(&self, id: EventId, node: &EventNode) -> Event {
+        let organizers = self.organizes.iter().filter(|((_, event_id), _)| *event_id == id);
+        let (primary, co): (Vec<_>, Vec<_>) = organizers.partition(|(_, primary)| **primary);
+
+        Event {
+            id,
+            name: node.name.clone(),
+            start_datetime: node.start_datetime,
+            end_datetime: node.end_datetime,
+            timezone: node.timezone.clone(),
+            capacity: node.capacity,
+            attendee_count: self
+                .registrations
+                .iter()
+                .filter(|((_, event_id), edge)| *event_id == id && edge.state.is_positive())
+                .count() as i64,
+            keywords: node.keywords.clone(),
+            organizer: primary.first().map(|((name, _), _)| name.clone()),
+            co_organizers: co.into_iter().map(|((name, _), _)| name.clone()).collect(),
+            featured_until: node.featured_until,
+            version: node.version,
+            archived_at: node.archived_at,
+        }
+    }
+
+    /// Events that are not archived, the only ones listings show.
+    pub fn active_events(&self) -> impl Iterator<Item = (&EventId, &EventNode)> {
+        self.events.iter().filter(|(_, node)| node.archived_at.is_none())
+    }
+
+    pub fn event(&self, id: EventId) -> Option<Event> {
+        self.events.get(&id).map(|node| self.to_event(id, node))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryGraph {
+    data: RwLock<GraphData>,
+}
+
+impl MemoryGraph {
+    pub fn read(&self) -> RwLockReadGuard<'_, GraphData> {
+        self.data.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, GraphData> {
+        self.data.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryConnection {
+    pub graph: Arc<MemoryGraph>,
+}
+
+impl MemoryConnection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,94 @@
+use neo4rs::{Error, Graph, query};
+
+/// A schema change applied once, in `version` order. Statements have to be idempotent
+/// (`IF NOT EXISTS`), as a crash between applying and recording one reruns it.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub statements: &'static [&'static str],
+}
+
+/// Append only: applied migrations are never edited, later changes get a new version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Uniqueness constraints",
+        statements: &[
+            "CREATE CONSTRAINT user_name IF NOT EXISTS
+             FOR (u:User)
+             REQUIRE u.name IS UNIQUE",
+            "CREATE CONSTRAINT event_id IF NOT EXISTS
+             FOR (e:Event)
+             REQUIRE e.id IS UNIQUE",
+            "CREATE CONSTRAINT eventKeyword_name IF NOT EXISTS
+             FOR (k:EventKeyword)
+             REQUIRE k.name IS UNIQUE",
+            // Keeps concurrent MERGEs of the event ID counter from creating two counters
+            "CREATE CONSTRAINT id_counter_name IF NOT EXISTS
+             FOR (c:IdCounter)
+             REQUIRE c.name IS UNIQUE",
+            "CREATE CONSTRAINT migration_version IF NOT EXISTS
+             FOR (m:Migration)
+             REQUIRE m.version IS UNIQUE",
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "Index on event start",
+        statements: &[
+            // Backs the period and date range filters and the default sort of event listings
+            "CREATE INDEX event_start IF NOT EXISTS
+             FOR (e:Event)
+             ON (e.startDatetime)",
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "Full-text index on event and keyword names",
+        statements: &[
+            // Backs `GET /events/search`, keyword hits are mapped to their events
+            "CREATE FULLTEXT INDEX event_search IF NOT EXISTS
+             FOR (n:Event|EventKeyword)
+             ON EACH [n.name]",
+        ],
+    },
+];
+
+/// Highest migration version recorded in the graph, 0 for a fresh database.
+async fn applied_version(graph: &Graph) -> Result<i64, Error> {
+    let mut result = graph
+        .execute(query("MATCH (m:Migration) RETURN coalesce(max(m.version), 0) AS version"))
+        .await?;
+
+    Ok(match result.next().await? {
+        Some(row) => row.get("version").unwrap_or(0),
+        None => 0,
+    })
+}
+
+/// Applies the migrations newer than the recorded version and records each of them
+/// as a `Migration` node. Returns the version the schema is at afterwards.
+pub async fn migrate(graph: &Graph) -> Result<i64, Error> {
+    let applied = applied_version(graph).await?;
+    let mut version = applied;
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > applied) {
+        for statement in migration.statements {
+            graph.run(query(statement)).await?;
+        }
+
+        graph
+            .run(
+                query(
+                    "MERGE (m:Migration {version: $version})
+                     ON CREATE SET m.description = $description, m.appliedAt = datetime()",
+                )
+                .param("version", migration.version)
+                .param("description", migration.description),
+            )
+            .await?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
@@ -1 +1,3 @@
+pub mod memory;
+pub mod migrations;
 pub mod neo4j;
\ No newline at end of file
@@ -1,5 +1,6 @@
 use neo4rs::*;
 use std::sync::Arc;
+use crate::db::migrations;
 
 pub struct Neo4jConnection {
     pub graph: Arc<Graph>,
@@ -15,6 +16,9 @@ impl Neo4jConnection {
 
         let graph = Arc::new(Graph::connect(config).await?);
 
+        let version = migrations::migrate(&graph).await?;
+        info!("Neo4j schema at version {}", version);
+
         Ok(Neo4jConnection { graph })
     }
 }
@@ -1,4 +1,5 @@
 #[macro_use] extern crate rocket;
+mod cli;
 mod db;
 mod routes;
 mod services;
@@ -6,27 +7,45 @@ mod models;
 mod utils;
 mod repo;
 
+use clap::Parser;
 use dotenv::dotenv;
+use rocket::fairing::AdHoc;
 use std::env;
+use std::process::ExitCode;
+use std::str::FromStr;
 use std::sync::Arc;
+use db::memory::MemoryConnection;
 use db::neo4j::Neo4jConnection;
+use crate::cli::{Cli, Command};
+use crate::routes::dataset::DatasetController;
 use crate::routes::events::EventController;
 use crate::routes::users::UserController;
-use crate::repo::events::EventRepository;
-use crate::repo::users::UserRepository;
-use crate::repo::users_events::UserEventRepository;
-use crate::services::events::EventService;
+use crate::repo::dataset::{DatasetRepository, Neo4jDatasetRepository};
+use crate::repo::events::{EventRepository, Neo4jEventRepository};
+use crate::repo::memory::dataset::InMemoryDatasetRepository;
+use crate::repo::memory::events::InMemoryEventRepository;
+use crate::repo::memory::users::InMemoryUserRepository;
+use crate::repo::memory::users_events::InMemoryUserEventRepository;
+use crate::repo::users::{Neo4jUserRepository, UserRepository};
+use crate::repo::users_events::{Neo4jUserEventRepository, UserEventRepository};
+use crate::services::dataset::DatasetService;
+use crate::services::events::{EventService, FeaturedConfig};
 use crate::services::users::UserService;
 use crate::services::users_events::UserEventService;
+use crate::utils::auth::TokenKeys;
 
 
-#[launch]
-async fn rocket() -> _ {
-    dotenv().ok();
+type Repositories = (
+    Box<dyn UserRepository>,
+    Box<dyn EventRepository>,
+    Box<dyn UserEventRepository>,
+    Box<dyn DatasetRepository>,
+);
+
+async fn neo4j_graph() -> Arc<neo4rs::Graph> {
     let neo4j_uri = env::var("DB_URI").unwrap_or_else(|_| "bolt://neo4j:7687".to_string());
     let neo4j_user = env::var("DB_USER").unwrap_or_else(|_| "neo4j".to_string());
     let neo4j_password = env::var("DB_PASSWORD").expect("NEO4J_PASSWORD not set");
-    let cors = rocket_cors::CorsOptions::default();
 
     let neo4j = Neo4jConnection::new(
         &neo4j_uri,
@@ -34,34 +53,154 @@ async fn rocket() -> _ {
         &neo4j_password
     ).await.expect("Failed to connect to Neo4j");
 
-    let graph = neo4j.graph;
+    neo4j.graph
+}
+
+async fn neo4j_repositories() -> Repositories {
+    let graph = neo4j_graph().await;
+
+    (
+        Box::new(Neo4jUserRepository::new(graph.clone())),
+        Box::new(Neo4jEventRepository::new(graph.clone())),
+        Box::new(Neo4jUserEventRepository::new(graph.clone())),
+        Box::new(Neo4jDatasetRepository::new(graph)),
+    )
+}
+
+/// Parses the environment variable, falling back to `default` when it is unset or invalid.
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Hard-deletes events archived for longer than `retention`, every `interval`.
+async fn purge_archived_events(
+    event_service: Arc<EventService>,
+    retention: chrono::Duration,
+    interval: std::time::Duration,
+) {
+    let mut ticks = rocket::tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        match event_service.purge_archived_events(retention).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} archived events", purged),
+            Err(e) => warn!("Failed to purge archived events: {}", e),
+        }
+    }
+}
+
+fn memory_repositories() -> Repositories {
+    let graph = MemoryConnection::new().graph;
 
-    let user_repo = UserRepository::new(graph.clone());
-    let event_repo = EventRepository::new(graph.clone());
-    let user_event_repo = UserEventRepository::new(graph);
+    (
+        Box::new(InMemoryUserRepository::new(graph.clone())),
+        Box::new(InMemoryEventRepository::new(graph.clone())),
+        Box::new(InMemoryUserEventRepository::new(graph.clone())),
+        Box::new(InMemoryDatasetRepository::new(graph)),
+    )
+}
+
+async fn rocket() -> rocket::Rocket<rocket::Build> {
+    let db_backend = env::var("DB_BACKEND").unwrap_or_else(|_| "neo4j".to_string());
+    let auth_secret = env::var("AUTH_SECRET").expect("AUTH_SECRET not set");
+    let token_ttl = env_or("AUTH_TOKEN_TTL", 24 * 60 * 60);
+    let token_keys = TokenKeys::new(&auth_secret, token_ttl);
+    let cors = rocket_cors::CorsOptions::default();
 
-    let user_service = Arc::new(UserService::new(user_repo));
-    let event_service = Arc::new(EventService::new(event_repo));
+    let (user_repo, event_repo, user_event_repo, dataset_repo) = match db_backend.as_str() {
+        "neo4j" => neo4j_repositories().await,
+        "memory" => memory_repositories(),
+        other => panic!("Unknown DB_BACKEND: {}", other),
+    };
+
+    let user_service = Arc::new(UserService::new(user_repo, token_keys.clone()));
+    let featured_defaults = FeaturedConfig::default();
+    let featured_config = FeaturedConfig {
+        size: env_or("FEATURED_EVENTS_SIZE", featured_defaults.size),
+        velocity_window: chrono::Duration::days(env_or(
+            "FEATURED_VELOCITY_DAYS",
+            featured_defaults.velocity_window.num_days(),
+        )),
+        cache_ttl: std::time::Duration::from_secs(env_or(
+            "FEATURED_CACHE_TTL",
+            featured_defaults.cache_ttl.as_secs(),
+        )),
+    };
+    let event_service = Arc::new(EventService::new(event_repo, featured_config));
+    let archive_retention = chrono::Duration::days(env_or("ARCHIVE_RETENTION_DAYS", 30));
+    // At least a second, a zero interval would make tokio panic
+    let purge_interval = std::time::Duration::from_secs(env_or("ARCHIVE_PURGE_INTERVAL", 60 * 60).max(1));
+    let purge_service = event_service.clone();
     let user_event_service = Arc::new(UserEventService::new(
         user_service.clone(),
         event_service.clone(),
         user_event_repo
     ));
 
+    if let (Ok(admin_name), Ok(admin_password)) = (env::var("ADMIN_NAME"), env::var("ADMIN_PASSWORD")) {
+        user_service
+            .ensure_admin(&admin_name, &admin_password)
+            .await
+            .expect("Failed to create admin user");
+    }
+
     let event_controller = EventController::new(
         event_service,
         user_event_service.clone()
     );
     let user_controller = UserController::new(
-        user_service,
+        user_service.clone(),
         user_event_service
     );
+    let dataset_controller = DatasetController::new(Arc::new(DatasetService::new(dataset_repo)));
 
     rocket::build()
+        .manage(token_keys)
+        .manage(user_service)
         .manage(event_controller)
         .manage(user_controller)
+        .manage(dataset_controller)
         .mount("/", EventController::routes())
         .mount("/", UserController::routes())
+        .mount("/", DatasetController::routes())
         .register("/", utils::error_catcher::catchers())
         .attach(cors.to_cors().expect("Failed to create CORS fairing"))
+        .attach(AdHoc::on_liftoff("Archived events purge", move |_| {
+            Box::pin(async move {
+                rocket::tokio::spawn(purge_archived_events(purge_service, archive_retention, purge_interval));
+            })
+        }))
+}
+
+async fn dataset_repository() -> Box<dyn DatasetRepository> {
+    Box::new(Neo4jDatasetRepository::new(neo4j_graph().await))
+}
+
+#[rocket::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+
+    match Cli::parse().command {
+        None => {
+            // An unhandled launch error reports itself when dropped, as with `#[launch]`.
+            let _ = rocket().await.launch().await;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Import(args)) => {
+            // Dry runs never open the database
+            let repo = if args.dry_run { None } else { Some(dataset_repository().await) };
+            cli::import::run(args, repo.as_deref()).await
+        }
+        Some(Command::Export(args)) => {
+            let service = DatasetService::new(dataset_repository().await);
+            cli::export::run(args, &service).await
+        }
+        Some(Command::Generate(args)) => {
+            let repo = if args.import { Some(dataset_repository().await) } else { None };
+            cli::generate::run(args, repo.as_deref()).await
+        }
+    }
 }
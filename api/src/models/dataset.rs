@@ -0,0 +1,147 @@
+use crate::models::event::{EventId, parse_datetime};
+use crate::models::registration::RsvpState;
+use crate::models::user::User;
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+/// A row of `user_events.csv`: a registration and the event it is for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserEventRow {
+    pub user_name: String,
+    pub event_id: EventId,
+    pub event_name: String,
+    pub start_datetime: DateTime<FixedOffset>,
+}
+
+/// A row of `event_keyword.csv`, and a `HAS` relationship.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventKeywordRow {
+    pub event_id: EventId,
+    pub keyword: String,
+}
+
+/// The stored properties of an event, without what is derived from its relationships.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRecord {
+    pub id: EventId,
+    pub name: String,
+    pub start_datetime: DateTime<FixedOffset>,
+    pub end_datetime: Option<DateTime<FixedOffset>>,
+    pub timezone: Option<String>,
+    pub capacity: Option<i64>,
+    pub archived_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A `REGISTERED_TO` relationship.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrationRecord {
+    pub user_name: String,
+    pub event_id: EventId,
+    pub state: RsvpState,
+    /// Unknown for registrations imported from CSV.
+    pub registered_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A `SIMILAR` relationship between two users, written by the GDS pipeline in `frp.cypher`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarityRecord {
+    pub user_name: String,
+    pub similar_user_name: String,
+    pub score: Option<f64>,
+}
+
+/// Every user, event and keyword of the graph and the relationships between them
+/// that the datasets are made of, each list in a stable order.
+#[derive(Debug, Default)]
+pub struct GraphDump {
+    pub users: Vec<User>,
+    pub events: Vec<EventRecord>,
+    pub keywords: Vec<String>,
+    pub registrations: Vec<RegistrationRecord>,
+    pub event_keywords: Vec<EventKeywordRow>,
+    pub similar: Vec<SimilarityRecord>,
+}
+
+pub const USER_EVENTS_COLUMNS: &[&str] = &["user_name", "event_id", "event_name", "start_datetime"];
+pub const EVENT_KEYWORD_COLUMNS: &[&str] = &["event_id", "keyword"];
+pub const SIMILAR_USERS_COLUMNS: &[&str] = &["user_name", "similar_user_name", "score"];
+
+/// The files an export is made of. The CSV files have the layouts `import.cypher` and the
+/// `import` command read, `similar_users.csv` only comes out of exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFile {
+    UserEvents,
+    EventKeywords,
+    SimilarUsers,
+    JsonLines,
+    GraphMl,
+}
+
+impl ExportFile {
+    pub const ALL: &'static [ExportFile] = &[
+        ExportFile::UserEvents,
+        ExportFile::EventKeywords,
+        ExportFile::SimilarUsers,
+        ExportFile::JsonLines,
+        ExportFile::GraphMl,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFile::UserEvents => "user_events.csv",
+            ExportFile::EventKeywords => "event_keyword.csv",
+            ExportFile::SimilarUsers => "similar_users.csv",
+            ExportFile::JsonLines => "graph.jsonl",
+            ExportFile::GraphMl => "graph.graphml",
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<ExportFile> {
+        ExportFile::ALL.iter().copied().find(|file| file.file_name() == name)
+    }
+}
+
+fn required(column: &str, value: &str) -> Result<String, String> {
+    match value.trim() {
+        "" => Err(format!("{} is empty", column)),
+        value => Ok(value.to_string()),
+    }
+}
+
+fn event_id(value: &str) -> Result<EventId, String> {
+    match value.trim().parse() {
+        Ok(id) if id > 0 => Ok(id),
+        _ => Err(format!("Invalid event_id \"{}\", expected a positive integer", value)),
+    }
+}
+
+impl UserEventRow {
+    /// Validates the values of one row, given in the order of [`USER_EVENTS_COLUMNS`].
+    /// Datetimes without an offset are taken as UTC, like `datetime()` in `LOAD CSV` does.
+    pub fn parse(values: &[&str]) -> Result<Self, String> {
+        let [user_name, id, event_name, start] = values else {
+            return Err(format!("Expected {} columns", USER_EVENTS_COLUMNS.len()));
+        };
+
+        Ok(Self {
+            user_name: required("user_name", user_name)?,
+            event_id: event_id(id)?,
+            event_name: required("event_name", event_name)?,
+            start_datetime: parse_datetime(start.trim(), None)?,
+        })
+    }
+}
+
+impl EventKeywordRow {
+    /// Validates the values of one row, given in the order of [`EVENT_KEYWORD_COLUMNS`].
+    pub fn parse(values: &[&str]) -> Result<Self, String> {
+        let [id, keyword] = values else {
+            return Err(format!("Expected {} columns", EVENT_KEYWORD_COLUMNS.len()));
+        };
+
+        Ok(Self {
+            event_id: event_id(id)?,
+            keyword: required("keyword", keyword)?,
+        })
+    }
+}
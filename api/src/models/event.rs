@@ -1,13 +1,42 @@
 use std::error::Error;
-use neo4rs::{Row};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use neo4rs::Row;
+use rocket::FromFormField;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Event IDs are Neo4j integers, so they are carried as `i64` end to end.
+pub type EventId = i64;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
-    pub id: u16,
+    pub id: EventId,
     pub name: String,
-    pub start_datetime: String,
-    pub keywords: Vec<String>
+    pub start_datetime: DateTime<FixedOffset>,
+    pub end_datetime: Option<DateTime<FixedOffset>>,
+    /// IANA timezone the event takes place in, e.g. `Europe/Warsaw`.
+    pub timezone: Option<String>,
+    /// Maximum number of registered attendees, unlimited when `None`.
+    pub capacity: Option<i64>,
+    /// Users going to or having attended the event.
+    pub attendee_count: i64,
+    pub keywords: Vec<String>,
+    pub organizer: Option<String>,
+    pub co_organizers: Vec<String>,
+    /// Pinned to the featured events by an admin until this datetime.
+    pub featured_until: Option<DateTime<FixedOffset>>,
+    /// Bumped on every edit and sent as the `ETag` of `GET /event/<id>`.
+    pub version: i64,
+    /// When the event was deleted. Archived events are hidden until restored or purged.
+    pub archived_at: Option<DateTime<FixedOffset>>,
+}
+
+/// An event found by `GET /events/search` with its relevance.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSearchHit {
+    #[serde(flatten)]
+    pub event: Event,
+    pub score: f64,
 }
 
 impl Event {
@@ -16,41 +45,619 @@ impl Event {
             id: row.get("eventId")?,
             name: row.get("eventName")?,
             start_datetime: row.get("start")?,
+            end_datetime: row.get("end")?,
+            timezone: row.get("timezone")?,
+            capacity: row.get("capacity")?,
+            attendee_count: row.get("attendeeCount")?,
             keywords: row.get("keywords")?,
+            organizer: row.get("organizer")?,
+            co_organizers: row.get("coOrganizers")?,
+            featured_until: row.get("featuredUntil")?,
+            version: row.get("version")?,
+            archived_at: row.get("archivedAt")?,
         })
     }
 
+    #[allow(dead_code)]
     pub fn display(&self) -> String {
         format!(
             "Event ID: {}\nName: {}\nStart: {}\nKeywords: {:?}",
             self.id, self.name, self.start_datetime, self.keywords
         )
     }
+
+    /// Strong entity tag of this version of the event.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    /// True for the primary organizer and for co-organizers.
+    pub fn is_organized_by(&self, user_name: &str) -> bool {
+        self.organizer.as_deref() == Some(user_name) || self.co_organizers.iter().any(|name| name == user_name)
+    }
+
+    /// Events without an end time are treated as taking no time at all.
+    pub fn duration(&self) -> Duration {
+        self.end_datetime
+            .map_or(Duration::zero(), |end| end - self.start_datetime)
+    }
+
+    /// True when both events take place at the same time. Back-to-back events,
+    /// where one ends exactly when the other starts, do not overlap.
+    pub fn overlaps(&self, other: &Event) -> bool {
+        let end = self.start_datetime + self.duration();
+        let other_end = other.start_datetime + other.duration();
+
+        self.start_datetime == other.start_datetime
+            || (self.start_datetime < other_end && other.start_datetime < end)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct EventUpdate {
     pub name: String,
     pub keywords: Vec<String>,
-    pub start_datetime: String
+    pub start_datetime: String,
+    #[serde(default)]
+    pub end_datetime: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub capacity: Option<i64>
+}
+
+/// The rules every event has to follow, checked on the whole event whether it is
+/// created, replaced or patched.
+fn validate_event(
+    name: &str,
+    start: DateTime<FixedOffset>,
+    end: Option<DateTime<FixedOffset>>,
+    capacity: Option<i64>,
+    keywords: &[String],
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Event name is required".to_string());
+    }
+
+    // Check if the event ends after it starts
+    if end.is_some_and(|end| end <= start) {
+        return Err("End datetime must be after start datetime".to_string());
+    }
+
+    // Check if the capacity leaves room for at least one attendee
+    if capacity.is_some_and(|capacity| capacity < 1) {
+        return Err("Capacity must be at least 1".to_string());
+    }
+
+    // Check if at least one keyword is provided
+    if keywords.is_empty() {
+        return Err("At least one keyword is required".to_string());
+    }
+
+    Ok(())
+}
+
+fn parse_timezone(timezone: Option<&str>) -> Result<Option<Tz>, String> {
+    timezone
+        .map(|tz| tz.parse::<Tz>().map_err(|_| format!("Unknown timezone: {}", tz)))
+        .transpose()
+}
+
+fn parse_start(start: &str, timezone: Option<Tz>) -> Result<DateTime<FixedOffset>, String> {
+    if start.trim().is_empty() {
+        return Err("Start datetime is required".to_string());
+    }
+    parse_datetime(start.trim(), timezone)
+}
+
+/// An empty end counts as no end.
+fn parse_end(end: Option<&str>, timezone: Option<Tz>) -> Result<Option<DateTime<FixedOffset>>, String> {
+    end.map(str::trim)
+        .filter(|end| !end.is_empty())
+        .map(|end| parse_datetime(end, timezone))
+        .transpose()
 }
 
 impl EventUpdate {
     pub fn validate(&self) -> Result<(), String> {
-        if self.name.trim().is_empty() {
-            return Err("Event name is required".to_string());
+        validate_event(&self.name, self.start()?, self.end()?, self.capacity, &self.keywords)
+    }
+
+    pub fn timezone(&self) -> Result<Option<Tz>, String> {
+        parse_timezone(self.timezone.as_deref())
+    }
+
+    /// The start as a typed datetime. Values without an offset are local to the
+    /// event's timezone (UTC when none is set), and the result is always expressed
+    /// in the event's timezone so clients get its local offset.
+    pub fn start(&self) -> Result<DateTime<FixedOffset>, String> {
+        parse_start(&self.start_datetime, self.timezone()?)
+    }
+
+    /// The optional end, parsed like [`EventUpdate::start`].
+    pub fn end(&self) -> Result<Option<DateTime<FixedOffset>>, String> {
+        parse_end(self.end_datetime.as_deref(), self.timezone()?)
+    }
+}
+
+/// Deserializes a field that is present, `null` included, as `Some`, so a missing
+/// field (left unchanged) can be told apart from a `null` one (cleared).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body of `PATCH /event/<id>`. Missing fields are left unchanged, `null` clears
+/// the optional ones.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventPatch {
+    pub name: Option<String>,
+    pub start_datetime: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub end_datetime: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub timezone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub capacity: Option<Option<i64>>,
+    /// Replaces every keyword of the event.
+    pub keywords: Option<Vec<String>>,
+    #[serde(default)]
+    pub add_keywords: Vec<String>,
+    #[serde(default)]
+    pub remove_keywords: Vec<String>,
+}
+
+/// A full update replaces every field.
+impl From<EventUpdate> for EventPatch {
+    fn from(update: EventUpdate) -> Self {
+        Self {
+            name: Some(update.name),
+            start_datetime: Some(update.start_datetime),
+            end_datetime: Some(update.end_datetime),
+            timezone: Some(update.timezone),
+            capacity: Some(update.capacity),
+            keywords: Some(update.keywords),
+            add_keywords: Vec::new(),
+            remove_keywords: Vec::new(),
         }
+    }
+}
+
+/// Same instant and same offset, so moving an event to another timezone counts as a change.
+fn same_datetime(a: &DateTime<FixedOffset>, b: &DateTime<FixedOffset>) -> bool {
+    a == b && a.offset() == b.offset()
+}
+
+impl EventPatch {
+    /// Applies the patch to `current`, validates the result like [`EventUpdate::validate`]
+    /// and returns only what differs from `current`.
+    pub fn changes(&self, current: &Event) -> Result<EventChanges, String> {
+        let name = self.name.clone().unwrap_or_else(|| current.name.clone());
 
-        // Check if start_datetime is empty
-        if self.start_datetime.trim().is_empty() {
-            return Err("Start datetime is required".to_string());
+        let timezone = match &self.timezone {
+            Some(timezone) => timezone.clone(),
+            None => current.timezone.clone(),
+        };
+        let tz = parse_timezone(timezone.as_deref())?;
+        // Kept datetimes are moved to the new timezone, like parsed ones
+        let localize = |datetime: DateTime<FixedOffset>| match tz {
+            Some(tz) => datetime.with_timezone(&tz).fixed_offset(),
+            None => datetime,
+        };
+
+        let start = match &self.start_datetime {
+            Some(start) => parse_start(start, tz)?,
+            None => localize(current.start_datetime),
+        };
+        let end = match &self.end_datetime {
+            Some(end) => parse_end(end.as_deref(), tz)?,
+            None => current.end_datetime.map(localize),
+        };
+        let capacity = self.capacity.unwrap_or(current.capacity);
+
+        let mut keywords = Vec::<String>::new();
+        for keyword in self.keywords.as_ref().unwrap_or(&current.keywords).iter().chain(&self.add_keywords) {
+            if !keywords.contains(keyword) && !self.remove_keywords.contains(keyword) {
+                keywords.push(keyword.clone());
+            }
         }
 
-        // Check if at least one keyword is provided
-        if self.keywords.is_empty() {
-            return Err("At least one keyword is required".to_string());
+        validate_event(&name, start, end, capacity, &keywords)?;
+
+        Ok(EventChanges {
+            name: (name != current.name).then_some(name),
+            start_datetime: (!same_datetime(&start, &current.start_datetime)).then_some(start),
+            end_datetime: match (end, current.end_datetime) {
+                (Some(end), Some(old)) if same_datetime(&end, &old) => None,
+                (None, None) => None,
+                _ => Some(end),
+            },
+            timezone: (timezone != current.timezone).then_some(timezone),
+            capacity: (capacity != current.capacity).then_some(capacity),
+            added_keywords: keywords
+                .iter()
+                .filter(|keyword| !current.keywords.contains(keyword))
+                .cloned()
+                .collect(),
+            removed_keywords: current
+                .keywords
+                .iter()
+                .filter(|keyword| !keywords.contains(keyword))
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+/// The fields of an event that an edit actually changes, `None` where it keeps the
+/// current value.
+#[derive(Debug, Default)]
+pub struct EventChanges {
+    pub name: Option<String>,
+    pub start_datetime: Option<DateTime<FixedOffset>>,
+    pub end_datetime: Option<Option<DateTime<FixedOffset>>>,
+    pub timezone: Option<Option<String>>,
+    pub capacity: Option<Option<i64>>,
+    pub added_keywords: Vec<String>,
+    pub removed_keywords: Vec<String>,
+}
+
+impl EventChanges {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.start_datetime.is_none()
+            && self.end_datetime.is_none()
+            && self.timezone.is_none()
+            && self.capacity.is_none()
+            && self.added_keywords.is_empty()
+            && self.removed_keywords.is_empty()
+    }
+}
+
+/// Body of `PUT /events/<id>/featured`.
+#[derive(Debug, Deserialize)]
+pub struct FeaturedUpdate {
+    pub featured_until: String,
+}
+
+impl FeaturedUpdate {
+    /// The end of the pin, which has to be in the future. Values without an offset are UTC.
+    pub fn until(&self) -> Result<DateTime<FixedOffset>, String> {
+        let until = parse_datetime(self.featured_until.trim(), None)?;
+        if until <= Utc::now() {
+            return Err("Featured until must be in the future".to_string());
         }
+        Ok(until)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum EventSort {
+    #[default]
+    #[field(value = "start")]
+    Start,
+    #[field(value = "name")]
+    Name,
+    /// By `attendee_count`.
+    #[field(value = "popularity")]
+    Popularity,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum SortOrder {
+    #[default]
+    #[field(value = "asc")]
+    Asc,
+    #[field(value = "desc")]
+    Desc,
+}
 
-        Ok(())
+/// Whether an event has started yet, by its start time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum EventPeriod {
+    #[field(value = "upcoming")]
+    Upcoming,
+    #[field(value = "past")]
+    Past,
+}
+
+/// Sorting and filtering query parameters of the event listings.
+#[derive(Debug, Clone, Default)]
+pub struct EventListParams {
+    pub sort: Option<EventSort>,
+    /// Defaults to descending for popularity and ascending otherwise.
+    pub order: Option<SortOrder>,
+    pub period: Option<EventPeriod>,
+    /// Only events starting at or after this datetime.
+    pub from: Option<String>,
+    /// Only events starting at or before this datetime.
+    pub to: Option<String>,
+}
+
+impl EventListParams {
+    pub fn to_filter(&self) -> Result<EventFilter, String> {
+        let sort = self.sort.unwrap_or_default();
+        let order = self.order.unwrap_or(match sort {
+            EventSort::Popularity => SortOrder::Desc,
+            EventSort::Start | EventSort::Name => SortOrder::Asc,
+        });
+
+        let parse = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| parse_datetime(value, None))
+                .transpose()
+        };
+        let from = parse(&self.from)?;
+        let to = parse(&self.to)?;
+
+        if from.zip(to).is_some_and(|(from, to)| from > to) {
+            return Err("\"from\" must not be after \"to\"".to_string());
+        }
+
+        Ok(EventFilter {
+            sort,
+            order,
+            period: self.period,
+            from,
+            to,
+        })
+    }
+}
+
+/// Validated [`EventListParams`].
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub sort: EventSort,
+    pub order: SortOrder,
+    pub period: Option<EventPeriod>,
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl EventFilter {
+    /// Whether an event starting at `start` passes the period and date range filters.
+    pub fn matches(&self, start: DateTime<FixedOffset>) -> bool {
+        let upcoming = start > Utc::now();
+        let in_period = match self.period {
+            Some(EventPeriod::Upcoming) => upcoming,
+            Some(EventPeriod::Past) => !upcoming,
+            None => true,
+        };
+
+        in_period
+            && self.from.is_none_or(|from| start >= from)
+            && self.to.is_none_or(|to| start <= to)
+    }
+}
+
+/// How the requested keywords of `/events/filter` have to match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum KeywordMatch {
+    /// Events having every requested keyword.
+    #[default]
+    #[field(value = "all")]
+    All,
+    /// Events having at least one requested keyword.
+    #[field(value = "any")]
+    Any,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeywordFilter {
+    pub keywords: Vec<String>,
+    /// Events having any of these keywords are left out.
+    pub excluded: Vec<String>,
+    pub mode: KeywordMatch,
+}
+
+impl KeywordFilter {
+    pub fn new(keywords: Vec<String>, excluded: Vec<String>, mode: KeywordMatch) -> Self {
+        let unique = |keywords: Vec<String>| {
+            let mut unique = Vec::<String>::new();
+            for keyword in keywords {
+                let keyword = keyword.trim().to_string();
+                if !keyword.is_empty() && !unique.contains(&keyword) {
+                    unique.push(keyword);
+                }
+            }
+            unique
+        };
+
+        Self {
+            keywords: unique(keywords),
+            excluded: unique(excluded),
+            mode,
+        }
+    }
+
+    /// The number of requested keywords among `event_keywords`, or `None` when an
+    /// event with these keywords does not pass the filter.
+    pub fn matched(&self, event_keywords: &[String]) -> Option<usize> {
+        if event_keywords.is_empty() || event_keywords.iter().any(|kw| self.excluded.contains(kw)) {
+            return None;
+        }
+
+        let matched = self.keywords.iter().filter(|kw| event_keywords.contains(kw)).count();
+        let passes = match self.mode {
+            KeywordMatch::All => matched == self.keywords.len(),
+            KeywordMatch::Any => self.keywords.is_empty() || matched > 0,
+        };
+
+        passes.then_some(matched)
+    }
+}
+
+/// Lowercase words of a search query, without punctuation.
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// How many typos a search term tolerates: none for very short terms, so they do
+/// not match almost everything, and up to two for long ones.
+pub fn search_fuzziness(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Parses RFC 3339, a local datetime or a plain date (midnight). Local values are
+/// interpreted in `timezone`, or UTC when it is `None`.
+pub fn parse_datetime(value: &str, timezone: Option<Tz>) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(match timezone {
+            Some(tz) => datetime.with_timezone(&tz).fixed_offset(),
+            None => datetime,
+        });
+    }
+
+    let local = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(Default::default()))
+        })
+        .ok_or_else(|| format!("Invalid datetime \"{}\", expected RFC 3339 (e.g. 2025-06-01T18:00:00+02:00)", value))?;
+
+    match timezone {
+        Some(tz) => tz
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|datetime| datetime.fixed_offset())
+            .ok_or_else(|| format!("{} does not exist in timezone {}", value, tz)),
+        None => Ok(Utc.from_utc_datetime(&local).fixed_offset()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::serde_json;
+
+    fn update(start: &str, end: Option<&str>, timezone: Option<&str>) -> EventUpdate {
+        EventUpdate {
+            name: "Concert".to_string(),
+            keywords: vec!["music".to_string()],
+            start_datetime: start.to_string(),
+            end_datetime: end.map(str::to_string),
+            timezone: timezone.map(str::to_string),
+            capacity: None,
+        }
+    }
+
+    fn current() -> Event {
+        Event {
+            id: 1,
+            name: "Concert".to_string(),
+            start_datetime: DateTime::parse_from_rfc3339("2025-06-01T18:00:00+02:00").unwrap(),
+            end_datetime: Some(DateTime::parse_from_rfc3339("2025-06-01T20:00:00+02:00").unwrap()),
+            timezone: Some("Europe/Warsaw".to_string()),
+            capacity: Some(10),
+            attendee_count: 0,
+            keywords: vec!["music".to_string(), "live".to_string()],
+            organizer: Some("org".to_string()),
+            co_organizers: Vec::new(),
+            featured_until: None,
+            version: 1,
+            archived_at: None,
+        }
     }
-}
\ No newline at end of file
+
+    fn patch(json: &str) -> EventPatch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn rfc3339_keeps_the_instant_and_takes_the_timezone_offset() {
+        let parsed = parse_datetime("2025-06-01T18:00:00+02:00", None).unwrap();
+        assert_eq!(parsed.to_rfc3339(), "2025-06-01T18:00:00+02:00");
+
+        let parsed = parse_datetime("2025-06-01T16:00:00Z", Some(chrono_tz::America::New_York)).unwrap();
+        assert_eq!(parsed.to_rfc3339(), "2025-06-01T12:00:00-04:00");
+    }
+
+    #[test]
+    fn local_datetimes_are_read_in_the_timezone() {
+        let warsaw = Some(chrono_tz::Europe::Warsaw);
+        assert_eq!(parse_datetime("2025-06-01T18:00", warsaw).unwrap().to_rfc3339(), "2025-06-01T18:00:00+02:00");
+        assert_eq!(parse_datetime("2025-01-01T18:00:00", warsaw).unwrap().to_rfc3339(), "2025-01-01T18:00:00+01:00");
+        assert_eq!(parse_datetime("2025-06-01T18:00:00", None).unwrap().to_rfc3339(), "2025-06-01T18:00:00+00:00");
+        assert_eq!(parse_datetime("2025-06-01", None).unwrap().to_rfc3339(), "2025-06-01T00:00:00+00:00");
+
+        // Skipped by the switch to summer time
+        assert!(parse_datetime("2025-03-30T02:30", warsaw).is_err());
+        assert!(parse_datetime("June 1st", None).is_err());
+    }
+
+    #[test]
+    fn validate_checks_the_timezone_and_the_end() {
+        assert!(update("2025-06-01T18:00", Some("2025-06-01T20:00"), Some("Europe/Warsaw")).validate().is_ok());
+        assert!(update("2025-06-01T18:00", None, Some("Mars/Olympus")).validate().is_err());
+        assert!(update("", None, None).validate().is_err());
+
+        assert!(update("2025-06-01T18:00", Some("2025-06-01T17:00"), None).validate().is_err());
+        assert!(update("2025-06-01T18:00", Some("2025-06-01T18:00"), None).validate().is_err());
+        // An empty end counts as no end
+        assert!(update("2025-06-01T18:00", Some(" "), None).validate().is_ok());
+    }
+
+    #[test]
+    fn missing_fields_are_kept_and_null_clears_them() {
+        let missing = patch(r#"{"name": "Renamed"}"#);
+        assert_eq!((&missing.end_datetime, &missing.timezone, missing.capacity), (&None, &None, None));
+        let changes = missing.changes(&current()).unwrap();
+        assert_eq!(changes.name.as_deref(), Some("Renamed"));
+        assert!(changes.start_datetime.is_none());
+        assert!(changes.end_datetime.is_none());
+        assert!(changes.timezone.is_none());
+        assert!(changes.capacity.is_none());
+
+        let null = patch(r#"{"end_datetime": null, "timezone": null, "capacity": null}"#);
+        assert_eq!((&null.end_datetime, &null.timezone, null.capacity), (&Some(None), &Some(None), Some(None)));
+        let changes = null.changes(&current()).unwrap();
+        assert!(changes.name.is_none());
+        assert_eq!(changes.end_datetime, Some(None));
+        assert_eq!(changes.timezone, Some(None));
+        assert_eq!(changes.capacity, Some(None));
+        // The start keeps its instant and offset without a timezone
+        assert!(changes.start_datetime.is_none());
+    }
+
+    #[test]
+    fn unchanged_values_and_keyword_operations() {
+        let changes = patch(r#"{"name": "Concert", "capacity": 10}"#).changes(&current()).unwrap();
+        assert!(changes.is_empty());
+
+        let changes = patch(r#"{"add_keywords": ["jazz", "music"], "remove_keywords": ["live"]}"#)
+            .changes(&current())
+            .unwrap();
+        assert_eq!(changes.added_keywords, ["jazz"]);
+        assert_eq!(changes.removed_keywords, ["live"]);
+
+        let changes = patch(r#"{"keywords": ["jazz"]}"#).changes(&current()).unwrap();
+        assert_eq!(changes.added_keywords, ["jazz"]);
+        assert_eq!(changes.removed_keywords, ["music", "live"]);
+    }
+
+    #[test]
+    fn patched_events_are_validated_whole() {
+        assert!(patch(r#"{"remove_keywords": ["music", "live"]}"#).changes(&current()).is_err());
+        assert!(patch(r#"{"capacity": 0}"#).changes(&current()).is_err());
+        // Moving the start past the kept end
+        assert!(patch(r#"{"start_datetime": "2025-06-01T21:00"}"#).changes(&current()).is_err());
+        assert!(patch(r#"{"start_datetime": "2025-06-01T21:00", "end_datetime": null}"#).changes(&current()).is_ok());
+    }
+}
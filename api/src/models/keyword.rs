@@ -0,0 +1,35 @@
+use crate::models::event::SortOrder;
+use rocket::FromFormField;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeywordStats {
+    pub name: String,
+    pub event_count: i64,
+    pub upcoming_event_count: i64,
+    /// `going` and `attended` registrations across the keyword's events.
+    pub registration_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum KeywordSort {
+    #[default]
+    #[field(value = "events")]
+    Events,
+    #[field(value = "upcoming")]
+    Upcoming,
+    #[field(value = "registrations")]
+    Registrations,
+    #[field(value = "name")]
+    Name,
+}
+
+impl KeywordSort {
+    /// Counts are listed highest first, names alphabetically.
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            KeywordSort::Name => SortOrder::Asc,
+            KeywordSort::Events | KeywordSort::Upcoming | KeywordSort::Registrations => SortOrder::Desc,
+        }
+    }
+}
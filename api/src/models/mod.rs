@@ -1,2 +1,6 @@
+pub mod dataset;
 pub mod event;
-pub mod user;
\ No newline at end of file
+pub mod keyword;
+pub mod registration;
+pub mod revision;
+pub mod user;
@@ -0,0 +1,89 @@
+use chrono::{DateTime, FixedOffset};
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a registration attempt for an event with a limited capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "status")]
+pub enum Registration {
+    Registered,
+    /// `position` is 1-based, the first user in the queue is promoted next.
+    Waitlisted { position: i64 },
+    /// The event is full and the user did not ask to join the waitlist.
+    Full,
+}
+
+/// RSVP state stored as the `state` property of `REGISTERED_TO`. Registrations
+/// imported without a state are treated as `going`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpState {
+    #[default]
+    #[field(value = "going")]
+    Going,
+    #[field(value = "maybe")]
+    Maybe,
+    #[field(value = "declined")]
+    Declined,
+    #[field(value = "attended")]
+    Attended,
+}
+
+impl RsvpState {
+    pub const ALL: &'static [RsvpState] =
+        &[RsvpState::Going, RsvpState::Maybe, RsvpState::Declined, RsvpState::Attended];
+
+    /// States that take a place at the event and count as interest in it, e.g. for
+    /// capacity, schedule conflicts and recommendations.
+    pub const POSITIVE: &'static [RsvpState] = &[RsvpState::Going, RsvpState::Attended];
+
+    /// Every state but `declined`, the default when listing a user's events.
+    pub const INTERESTED: &'static [RsvpState] = &[RsvpState::Going, RsvpState::Maybe, RsvpState::Attended];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RsvpState::Going => "going",
+            RsvpState::Maybe => "maybe",
+            RsvpState::Declined => "declined",
+            RsvpState::Attended => "attended",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RsvpState> {
+        match name {
+            "going" => Some(RsvpState::Going),
+            "maybe" => Some(RsvpState::Maybe),
+            "declined" => Some(RsvpState::Declined),
+            "attended" => Some(RsvpState::Attended),
+            _ => None,
+        }
+    }
+
+    pub fn is_positive(&self) -> bool {
+        RsvpState::POSITIVE.contains(self)
+    }
+
+    /// Names of `states` as stored in the graph, for use as a query parameter.
+    pub fn names(states: &[RsvpState]) -> Vec<&'static str> {
+        states.iter().map(RsvpState::as_str).collect()
+    }
+}
+
+/// A user registered to an event, as listed by `GET /events/<id>/attendees`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attendee {
+    pub name: String,
+    pub state: RsvpState,
+    /// Unknown for registrations imported from CSV.
+    pub registered_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum AttendeeSort {
+    /// Earliest registrations first, the ones without a time last.
+    #[default]
+    #[field(value = "registered_at")]
+    RegisteredAt,
+    #[field(value = "name")]
+    Name,
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, FixedOffset};
+use rocket::serde::json::serde_json::{Map, Value, json};
+use serde::{Deserialize, Serialize};
+use crate::models::event::Event;
+
+/// What a revision did to its event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionAction {
+    Created,
+    Edited,
+    Deleted,
+    Restored,
+    RolledBack,
+}
+
+impl RevisionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionAction::Created => "created",
+            RevisionAction::Edited => "edited",
+            RevisionAction::Deleted => "deleted",
+            RevisionAction::Restored => "restored",
+            RevisionAction::RolledBack => "rolled_back",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RevisionAction> {
+        match name {
+            "created" => Some(RevisionAction::Created),
+            "edited" => Some(RevisionAction::Edited),
+            "deleted" => Some(RevisionAction::Deleted),
+            "restored" => Some(RevisionAction::Restored),
+            "rolled_back" => Some(RevisionAction::RolledBack),
+            _ => None,
+        }
+    }
+}
+
+/// One field of an event before and after a revision, `null` when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// An immutable entry of `GET /event/<id>/history`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    /// 1-based and consecutive per event.
+    pub number: i64,
+    pub action: RevisionAction,
+    /// The user who made the change.
+    pub user: String,
+    pub timestamp: DateTime<FixedOffset>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Fields that edits, and so rollbacks, can change. Their names match `EventPatch`.
+const EDITABLE_FIELDS: &[&str] = &["name", "start_datetime", "end_datetime", "timezone", "capacity", "keywords"];
+
+/// The fields a revision tracks, keywords sorted so their order does not count as a change.
+fn tracked_fields(event: &Event) -> Vec<(&'static str, Value)> {
+    let mut keywords = event.keywords.clone();
+    keywords.sort();
+
+    vec![
+        ("name", json!(event.name)),
+        ("start_datetime", json!(event.start_datetime.to_rfc3339())),
+        ("end_datetime", json!(event.end_datetime.map(|end| end.to_rfc3339()))),
+        ("timezone", json!(event.timezone)),
+        ("capacity", json!(event.capacity)),
+        ("keywords", json!(keywords)),
+        ("archived_at", json!(event.archived_at.map(|archived_at| archived_at.to_rfc3339()))),
+    ]
+}
+
+/// Field-level diff between two states of an event, `before` being `None` for a new event.
+pub fn diff(before: Option<&Event>, after: &Event) -> Vec<FieldChange> {
+    let before: Vec<Value> = match before {
+        Some(event) => tracked_fields(event).into_iter().map(|(_, value)| value).collect(),
+        None => Vec::new(),
+    };
+
+    tracked_fields(after)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (field, to))| FieldChange {
+            field: field.to_string(),
+            from: before.get(i).cloned().unwrap_or(Value::Null),
+            to,
+        })
+        .filter(|change| change.from != change.to)
+        .collect()
+}
+
+/// The editable fields of the event as they were right after the first of `revisions`,
+/// given every revision from that one on, oldest first. Each field is taken from the
+/// earliest later revision that changed it, or from `current` when none did.
+pub fn fields_at(current: &Event, revisions: &[Revision]) -> Map<String, Value> {
+    let mut fields: Map<String, Value> = tracked_fields(current)
+        .into_iter()
+        .filter(|(field, _)| EDITABLE_FIELDS.contains(field))
+        .map(|(field, value)| (field.to_string(), value))
+        .collect();
+
+    for revision in revisions.iter().skip(1).rev() {
+        for change in &revision.changes {
+            if let Some(value) = fields.get_mut(&change.field) {
+                *value = change.from.clone();
+            }
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::serde_json;
+
+    fn event(name: &str, capacity: Option<i64>, keywords: &[&str]) -> Event {
+        Event {
+            id: 1,
+            name: name.to_string(),
+            start_datetime: DateTime::parse_from_rfc3339("2025-06-01T18:00:00+02:00").unwrap(),
+            end_datetime: None,
+            timezone: None,
+            capacity,
+            attendee_count: 0,
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            organizer: Some("org".to_string()),
+            co_organizers: Vec::new(),
+            featured_until: None,
+            version: 1,
+            archived_at: None,
+        }
+    }
+
+    /// The revision from `before` to `after`, its changes read back from the JSON string
+    /// they are stored as.
+    fn revision(number: i64, action: RevisionAction, before: Option<&Event>, after: &Event) -> Revision {
+        let stored = serde_json::to_string(&diff(before, after)).unwrap();
+        Revision {
+            number,
+            action,
+            user: "org".to_string(),
+            timestamp: after.start_datetime,
+            changes: serde_json::from_str(&stored).unwrap(),
+        }
+    }
+
+    #[test]
+    fn diff_keeps_only_changed_fields() {
+        let created = event("Concert", Some(10), &["music", "live"]);
+        let reordered = event("Concert", Some(10), &["live", "music"]);
+        assert!(diff(Some(&created), &reordered).is_empty());
+
+        let renamed = event("Gig", Some(10), &["live", "music"]);
+        assert_eq!(
+            diff(Some(&created), &renamed),
+            [FieldChange { field: "name".to_string(), from: json!("Concert"), to: json!("Gig") }]
+        );
+        // Unset fields of a new event are left out
+        assert_eq!(diff(None, &created).len(), 4);
+    }
+
+    #[test]
+    fn fields_at_undoes_the_later_revisions() {
+        let first = event("Concert", Some(10), &["music"]);
+        let second = event("Gig", None, &["music"]);
+        let mut third = event("Gig", None, &["music", "jazz"]);
+        third.archived_at = Some(third.start_datetime);
+        let revisions = [
+            revision(1, RevisionAction::Created, None, &first),
+            revision(2, RevisionAction::Edited, Some(&first), &second),
+            revision(3, RevisionAction::Deleted, Some(&second), &third),
+        ];
+
+        let fields = fields_at(&third, &revisions);
+        assert_eq!(fields["name"], json!("Concert"));
+        assert_eq!(fields["capacity"], json!(10));
+        assert_eq!(fields["keywords"], json!(["music"]));
+        assert!(!fields.contains_key("archived_at"));
+
+        let fields = fields_at(&third, &revisions[1..]);
+        assert_eq!(fields["name"], json!("Gig"));
+        assert_eq!(fields["capacity"], Value::Null);
+        assert_eq!(fields["keywords"], json!(["music"]));
+
+        // The latest revision leaves the current fields
+        assert_eq!(fields_at(&third, &revisions[2..])["keywords"], json!(["jazz", "music"]));
+    }
+}
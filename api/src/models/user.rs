@@ -1,6 +1,81 @@
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Organizer,
+    #[default]
+    Attendee,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Organizer => "organizer",
+            Role::Attendee => "attendee",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
-    name: String
-}
\ No newline at end of file
+    pub name: String,
+    #[serde(default)]
+    pub role: Role
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserUpdate {
+    pub name: String,
+    pub password: Option<String>
+}
+
+impl UserUpdate {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("User name is required".to_string());
+        }
+
+        if self.name.contains('/') {
+            return Err("User name cannot contain '/'".to_string());
+        }
+
+        if let Some(password) = &self.password {
+            validate_password(password)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < 8 {
+        return Err("Password must be at least 8 characters long".to_string());
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordUpdate {
+    pub password: String
+}
+
+impl PasswordUpdate {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_password(&self.password)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub name: String,
+    pub password: String
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleUpdate {
+    pub role: Role
+}
@@ -0,0 +1,285 @@
+use crate::models::dataset::{EventKeywordRow, EventRecord, GraphDump, RegistrationRecord, SimilarityRecord, UserEventRow};
+use crate::models::event::EventId;
+use crate::repo::RepoError;
+use crate::repo::RepoError::Other;
+use neo4rs::{BoltType, Graph, Row, query};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Bulk writes of the `neo4j_import` CSV datasets. Writes only ever add nodes and
+/// relationships, and fill in properties that are missing, unless the graph is cleared first.
+#[rocket::async_trait]
+pub trait DatasetRepository: Send + Sync {
+    /// Deletes everything but the schema migrations.
+    async fn clear(&self) -> Result<(), RepoError>;
+
+    /// Whether the graph holds nothing but the schema migrations.
+    async fn is_empty(&self) -> Result<bool, RepoError>;
+
+    /// Merges the events of `rows` in one transaction, so they exist without registrations.
+    async fn import_events(&self, rows: &[EventRecord]) -> Result<(), RepoError>;
+
+    /// Merges the users, events and registrations of `rows` in one transaction.
+    async fn import_user_events(&self, rows: &[UserEventRow]) -> Result<(), RepoError>;
+
+    /// Merges the keywords of `rows` in one transaction and returns the IDs of the events
+    /// that do not exist, whose rows are skipped.
+    async fn import_event_keywords(&self, rows: &[EventKeywordRow]) -> Result<Vec<EventId>, RepoError>;
+
+    /// Moves the event ID counter past the highest event ID.
+    async fn sync_event_id_counter(&self) -> Result<(), RepoError>;
+
+    /// Reads the whole dataset, users ordered by name and events by ID.
+    async fn dump(&self) -> Result<GraphDump, RepoError>;
+}
+
+pub struct Neo4jDatasetRepository {
+    graph: Arc<Graph>,
+}
+
+impl Neo4jDatasetRepository {
+    pub fn new(graph: Arc<Graph>) -> Self {
+        Self { graph }
+    }
+
+    /// Runs the query and maps every row it returns.
+    async fn collect<T>(&self, cypher: &str, map: fn(&Row) -> Result<T, neo4rs::DeError>) -> Result<Vec<T>, RepoError> {
+        let mut result = self.graph.execute(query(cypher)).await?;
+        let mut items = Vec::new();
+        while let Some(row) = result.next().await? {
+            items.push(map(&row).map_err(|e| Other(e.to_string()))?);
+        }
+        Ok(items)
+    }
+}
+
+#[rocket::async_trait]
+impl DatasetRepository for Neo4jDatasetRepository {
+    async fn clear(&self) -> Result<(), RepoError> {
+        self.graph
+            .run(query(
+                "\
+                MATCH (n) WHERE NOT n:Migration
+                CALL (n) { DETACH DELETE n } IN TRANSACTIONS OF 10000 ROWS
+                ",
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn is_empty(&self) -> Result<bool, RepoError> {
+        let empty = self
+            .collect("RETURN NOT EXISTS { MATCH (n) WHERE NOT n:Migration } AS empty", |row| row.get("empty"))
+            .await?;
+        Ok(empty.first().copied().unwrap_or(true))
+    }
+
+    async fn import_events(&self, rows: &[EventRecord]) -> Result<(), RepoError> {
+        let rows: Vec<HashMap<&str, BoltType>> = rows
+            .iter()
+            .map(|row| {
+                HashMap::from([
+                    ("id", row.id.into()),
+                    ("name", row.name.as_str().into()),
+                    ("startDatetime", row.start_datetime.to_rfc3339().into()),
+                    ("endDatetime", row.end_datetime.map(|end| end.to_rfc3339()).into()),
+                    ("timezone", row.timezone.clone().into()),
+                    ("capacity", row.capacity.into()),
+                    ("archivedAt", row.archived_at.map(|archived_at| archived_at.to_rfc3339()).into()),
+                ])
+            })
+            .collect();
+
+        self.graph
+            .run(
+                query(
+                    "\
+                UNWIND $rows AS row
+                MERGE (e:Event {id: row.id})
+                  ON CREATE SET e.version = 1
+                SET e.name = coalesce(e.name, row.name),
+                    e.startDatetime = coalesce(e.startDatetime, datetime(row.startDatetime)),
+                    e.endDatetime = coalesce(e.endDatetime, datetime(row.endDatetime)),
+                    e.timezone = coalesce(e.timezone, row.timezone),
+                    e.capacity = coalesce(e.capacity, row.capacity),
+                    e.archivedAt = coalesce(e.archivedAt, datetime(row.archivedAt))
+                ",
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn import_user_events(&self, rows: &[UserEventRow]) -> Result<(), RepoError> {
+        let rows: Vec<HashMap<&str, BoltType>> = rows
+            .iter()
+            .map(|row| {
+                HashMap::from([
+                    ("userName", row.user_name.as_str().into()),
+                    ("eventId", row.event_id.into()),
+                    ("eventName", row.event_name.as_str().into()),
+                    ("startDatetime", row.start_datetime.to_rfc3339().into()),
+                ])
+            })
+            .collect();
+
+        self.graph
+            .run(
+                query(
+                    "\
+                UNWIND $rows AS row
+                MERGE (u:User {name: row.userName})
+                MERGE (e:Event {id: row.eventId})
+                  ON CREATE SET e.version = 1
+                SET e.name = coalesce(e.name, row.eventName),
+                    e.startDatetime = coalesce(e.startDatetime, datetime(row.startDatetime))
+                MERGE (u)-[:REGISTERED_TO]->(e)
+                ",
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn import_event_keywords(&self, rows: &[EventKeywordRow]) -> Result<Vec<EventId>, RepoError> {
+        let rows: Vec<HashMap<&str, BoltType>> = rows
+            .iter()
+            .map(|row| {
+                HashMap::from([
+                    ("eventId", row.event_id.into()),
+                    ("keyword", row.keyword.as_str().into()),
+                ])
+            })
+            .collect();
+
+        let mut result = self
+            .graph
+            .execute(
+                query(
+                    "\
+                UNWIND $rows AS row
+                OPTIONAL MATCH (e:Event {id: row.eventId})
+                FOREACH (_ IN CASE WHEN e IS NULL THEN [] ELSE [1] END |
+                  MERGE (k:EventKeyword {name: row.keyword})
+                  MERGE (e)-[:HAS]->(k))
+                WITH row, e WHERE e IS NULL
+                RETURN collect(DISTINCT row.eventId) AS unknown
+                ",
+                )
+                .param("rows", rows),
+            )
+            .await?;
+
+        match result.next().await? {
+            Some(row) => row.get("unknown").map_err(|e| Other(e.to_string())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn sync_event_id_counter(&self) -> Result<(), RepoError> {
+        self.graph
+            .run(query(
+                "\
+                MATCH (e:Event)
+                WITH max(e.id) AS maxId
+                WHERE maxId IS NOT NULL
+                MERGE (c:IdCounter {name: 'Event'})
+                SET c.value = CASE WHEN c.value IS NULL OR c.value < maxId THEN maxId ELSE c.value END
+                ",
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn dump(&self) -> Result<GraphDump, RepoError> {
+        let users = self.collect("MATCH (u:User) RETURN u ORDER BY u.name", |row| row.get("u")).await?;
+
+        let events = self
+            .collect(
+                "\
+                MATCH (e:Event)
+                RETURN e.id AS id, e.name AS name, e.startDatetime AS start, e.endDatetime AS end,
+                       e.timezone AS timezone, e.capacity AS capacity, e.archivedAt AS archivedAt
+                ORDER BY id
+                ",
+                |row| {
+                    Ok(EventRecord {
+                        id: row.get("id")?,
+                        name: row.get("name")?,
+                        start_datetime: row.get("start")?,
+                        end_datetime: row.get("end")?,
+                        timezone: row.get("timezone")?,
+                        capacity: row.get("capacity")?,
+                        archived_at: row.get("archivedAt")?,
+                    })
+                },
+            )
+            .await?;
+
+        let keywords = self
+            .collect("MATCH (k:EventKeyword) RETURN k.name AS name ORDER BY name", |row| row.get("name"))
+            .await?;
+
+        let registrations = self
+            .collect(
+                "\
+                MATCH (u:User)-[r:REGISTERED_TO]->(e:Event)
+                RETURN u.name AS userName, e.id AS eventId, coalesce(r.state, 'going') AS state, r.registeredAt AS registeredAt
+                ORDER BY userName, eventId
+                ",
+                |row| {
+                    Ok(RegistrationRecord {
+                        user_name: row.get("userName")?,
+                        event_id: row.get("eventId")?,
+                        state: row.get("state")?,
+                        registered_at: row.get("registeredAt")?,
+                    })
+                },
+            )
+            .await?;
+
+        let event_keywords = self
+            .collect(
+                "\
+                MATCH (e:Event)-[:HAS]->(k:EventKeyword)
+                RETURN e.id AS eventId, k.name AS keyword
+                ORDER BY eventId, keyword
+                ",
+                |row| {
+                    Ok(EventKeywordRow {
+                        event_id: row.get("eventId")?,
+                        keyword: row.get("keyword")?,
+                    })
+                },
+            )
+            .await?;
+
+        let similar = self
+            .collect(
+                "\
+                MATCH (u:User)-[s:SIMILAR]->(other:User)
+                RETURN u.name AS userName, other.name AS similarUserName, s.score AS score
+                ORDER BY userName, similarUserName
+                ",
+                |row| {
+                    Ok(SimilarityRecord {
+                        user_name: row.get("userName")?,
+                        similar_user_name: row.get("similarUserName")?,
+                        score: row.get("score")?,
+                    })
+                },
+            )
+            .await?;
+
+        Ok(GraphDump {
+            users,
+            events,
+            keywords,
+            registrations,
+            event_keywords,
+            similar,
+        })
+    }
+}
@@ -1,10 +1,20 @@
-use crate::models::event::{Event, EventUpdate};
-use neo4rs::{Graph, Result, Row, query};
+use crate::models::event::{
+    Event, EventChanges, EventFilter, EventId, EventPeriod, EventSearchHit, EventSort, EventUpdate, KeywordFilter, KeywordMatch,
+    SortOrder, search_fuzziness,
+};
+use crate::models::keyword::{KeywordSort, KeywordStats};
+use crate::models::registration::RsvpState;
+use crate::models::revision::{self, Revision, RevisionAction};
+use rocket::serde::json::serde_json;
+use chrono::{DateTime, FixedOffset};
+use neo4rs::{Graph, Query, Row, Txn, query};
 use rocket::http::Status;
 use std::sync::Arc;
 use thiserror::Error;
 use crate::repo::ApiError;
+use crate::repo::users_events::PROMOTE_ALL_FROM_WAITLIST;
 use crate::repo::events::EventRepoError::Other;
+use crate::utils::pagination::skip;
 
 #[derive(Error, Debug)]
 pub enum EventRepoError {
@@ -12,7 +22,13 @@ pub enum EventRepoError {
     DatabaseError(#[from] neo4rs::Error),
 
     #[error("Event not found with id: {0}")]
-    NotFound(u16),
+    NotFound(EventId),
+
+    #[error("Event {0} was modified since it was read")]
+    VersionMismatch(EventId),
+
+    #[error("Event {0} is not archived")]
+    NotArchived(EventId),
 
     #[error("Error parsing event: {0}")]
     ParseError(String),
@@ -25,31 +41,331 @@ impl ApiError for EventRepoError {
     fn status(&self) -> Status {
         match self {
             EventRepoError::NotFound(_) => Status::NotFound,
+            EventRepoError::VersionMismatch(_) => Status::PreconditionFailed,
+            EventRepoError::NotArchived(_) => Status::Conflict,
             _ => Status::BadRequest,
         }
     }
 }
-pub struct EventRepository {
+/// Columns read by `Event::from_row` for an event bound to `e`. Queries add
+/// their own `keywords` column next to them.
+const EVENT_COLUMNS: &str = "
+    e.id               AS eventId,
+    e.name             AS eventName,
+    e.startDatetime    AS start,
+    e.endDatetime      AS end,
+    e.timezone         AS timezone,
+    e.capacity         AS capacity,
+    coalesce(e.version, 0) AS version,
+    COUNT { (:User)-[att:REGISTERED_TO]->(e) WHERE coalesce(att.state, 'going') IN $positiveStates } AS attendeeCount,
+    [(org:User)-[orgRel:ORGANIZES]->(e) WHERE orgRel.primary | org.name][0]      AS organizer,
+    [(org:User)-[orgRel:ORGANIZES]->(e) WHERE NOT orgRel.primary | org.name]    AS coOrganizers,
+    e.featuredUntil    AS featuredUntil,
+    e.archivedAt       AS archivedAt";
+
+/// The text of `cypher` with its `{event_columns}` placeholder expanded to [`EVENT_COLUMNS`].
+pub fn with_event_columns(cypher: &str) -> String {
+    cypher.replace("{event_columns}", EVENT_COLUMNS)
+}
+
+/// Builds a query with its `{event_columns}` placeholder expanded to [`EVENT_COLUMNS`],
+/// binding the `$positiveStates` they count attendees with.
+pub fn event_query(cypher: &str) -> Query {
+    query(&with_event_columns(cypher))
+        .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
+}
+
+/// The keywords without repeats, in the order they first appear.
+pub(crate) fn unique_keywords(keywords: Vec<String>) -> Vec<String> {
+    let mut unique = Vec::<String>::new();
+    for keyword in keywords {
+        if !unique.contains(&keyword) {
+            unique.push(keyword);
+        }
+    }
+    unique
+}
+
+/// Runs a query returning a single `total` column. Totals of paginated lists are counted
+/// on their own so they don't depend on the page, which may be past the last result.
+pub async fn count(graph: &Graph, query: Query) -> Result<u32, neo4rs::Error> {
+    let row = graph.execute(query).await?.next().await?;
+    Ok(row.and_then(|row| row.get::<i64>("total").ok()).unwrap_or(0) as u32)
+}
+
+/// Runs `query` in `txn` and returns its first row, reading the rest so the
+/// transaction can go on with its next query.
+async fn first_row(txn: &mut Txn, query: Query) -> Result<Option<Row>, neo4rs::Error> {
+    let mut rows = txn.execute(query).await?;
+    let first = rows.next(txn.handle()).await?;
+    while rows.next(txn.handle()).await?.is_some() {}
+    Ok(first)
+}
+
+/// Commits `txn` if `result` is a success and rolls it back otherwise.
+async fn finish<T>(txn: Txn, result: Result<T, EventRepoError>) -> Result<T, EventRepoError> {
+    match result {
+        Ok(value) => {
+            txn.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            // The error that failed the transaction is the one worth reporting
+            let _ = txn.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// Reads the event in `txn` and write-locks it until the transaction ends, so its
+/// revision is computed against the state the change was applied to.
+async fn lock_event(txn: &mut Txn, id: EventId) -> Result<Option<Event>, EventRepoError> {
+    let row = first_row(
+        txn,
+        event_query(
+            "\
+            MATCH (e:Event {id: $id})
+            SET e._lock = true
+            REMOVE e._lock
+            WITH e
+            OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+            RETURN
+                {event_columns},
+                collect(k.name)    AS keywords;",
+        )
+        .param("id", id),
+    )
+    .await?;
+
+    row.map(|row| Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string())))
+        .transpose()
+}
+
+/// Appends the revision taking the event from `before` to `after` to its history,
+/// numbered after the last one. The event is locked, or only just created, by then, so
+/// concurrent revisions can't get the same number.
+async fn add_revision(
+    txn: &mut Txn,
+    action: RevisionAction,
+    user: &str,
+    before: Option<&Event>,
+    after: &Event,
+) -> Result<(), EventRepoError> {
+    let changes = serde_json::to_string(&revision::diff(before, after)).map_err(|e| Other(e.to_string()))?;
+
+    let row = first_row(
+        txn,
+        query(
+            "\
+            MATCH (e:Event { id: $eventId })
+            WITH e, COUNT { (e)-[:HAS_REVISION]->(:EventRevision) } + 1 AS number
+            // The event ID is kept on the revision so it outlives the purge of its event
+            CREATE (e)-[:HAS_REVISION]->(r:EventRevision {
+                eventId: e.id,
+                number: number,
+                action: $action,
+                user: $user,
+                timestamp: datetime(),
+                changes: $changes
+            })
+            RETURN r.number AS number;",
+        )
+        .param("eventId", after.id)
+        .param("action", action.as_str())
+        .param("user", user)
+        .param("changes", changes),
+    )
+    .await?;
+
+    match row {
+        Some(_) => Ok(()),
+        None => Err(EventRepoError::NotFound(after.id)),
+    }
+}
+
+/// Period and date range conditions of an [`EventFilter`] on the event bound to `e`,
+/// which also leave out archived events.
+const EVENT_FILTER: &str = "
+    e.archivedAt IS NULL
+    AND ($upcoming IS NULL OR (e.startDatetime > datetime()) = $upcoming)
+    AND ($from IS NULL OR e.startDatetime >= datetime($from))
+    AND ($to IS NULL OR e.startDatetime <= datetime($to))";
+
+/// Like [`event_query`], also expanding `{event_filter}` to the conditions of `filter`
+/// and `{event_order}` to its sort order over the event columns.
+pub fn filtered_event_query(cypher: &str, filter: &EventFilter) -> Query {
+    let direction = match filter.order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    let order = match filter.sort {
+        EventSort::Start => format!("start {}, eventId", direction),
+        EventSort::Name => format!("eventName {}, eventId", direction),
+        EventSort::Popularity => format!("attendeeCount {}, start, eventId", direction),
+    };
+
+    let upcoming = filter.period.map(|period| period == EventPeriod::Upcoming);
+
+    event_query(
+        &cypher
+            .replace("{event_filter}", EVENT_FILTER)
+            .replace("{event_order}", &order),
+    )
+    .param("upcoming", upcoming)
+    .param("from", filter.from.map(|from| from.to_rfc3339()))
+    .param("to", filter.to.map(|to| to.to_rfc3339()))
+}
+
+/// Columns read by [`revision_from_row`] for a revision bound to `r`.
+const REVISION_COLUMNS: &str = "
+    r.number    AS number,
+    r.action    AS action,
+    r.user      AS user,
+    r.timestamp AS timestamp,
+    r.changes   AS changes";
+
+/// Revisions keep their changes as a JSON string, Neo4j properties cannot hold maps.
+fn revision_from_row(row: &neo4rs::Row) -> Result<Revision, EventRepoError> {
+    let action: String = row.get("action").map_err(|e| EventRepoError::ParseError(e.to_string()))?;
+    let changes: String = row.get("changes").map_err(|e| EventRepoError::ParseError(e.to_string()))?;
+
+    Ok(Revision {
+        number: row.get("number").map_err(|e| EventRepoError::ParseError(e.to_string()))?,
+        action: RevisionAction::from_name(&action)
+            .ok_or_else(|| EventRepoError::ParseError(format!("Unknown revision action: {}", action)))?,
+        user: row.get("user").map_err(|e| EventRepoError::ParseError(e.to_string()))?,
+        timestamp: row.get("timestamp").map_err(|e| EventRepoError::ParseError(e.to_string()))?,
+        changes: serde_json::from_str(&changes).map_err(|e| EventRepoError::ParseError(e.to_string()))?,
+    })
+}
+
+#[rocket::async_trait]
+pub trait EventRepository: Send + Sync {
+    async fn find_by_id(&self, id: EventId) -> Result<Event, EventRepoError>;
+
+    /// One page of the events passing `filter`, in its order, and their total.
+    async fn find_all(
+        &self,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError>;
+
+    /// Creates the event with `organizer` as its primary organizer.
+    ///
+    /// Like every method changing an event, it records the change as a revision by the
+    /// acting user, together with the change itself: either both are written or neither is.
+    async fn add(&self, event: EventUpdate, organizer: &str) -> Result<Event, EventRepoError>;
+
+    /// Archives the event: it is hidden everywhere but keeps its registrations until purged.
+    /// Its featured pin is dropped.
+    async fn remove(&self, id: EventId, user: &str) -> Result<String, EventRepoError>;
+
+    /// Brings an archived event back.
+    async fn restore(&self, id: EventId, user: &str) -> Result<Event, EventRepoError>;
+
+    /// One page of archived events, only those organized by `organizer` when set, and their total.
+    async fn find_archived(
+        &self,
+        organizer: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError>;
+
+    /// One page of the event's revisions, newest first, and their total.
+    async fn find_revisions(&self, id: EventId, page: u32, limit: u32) -> Result<(Vec<Revision>, u32), EventRepoError>;
+
+    /// The event's revisions numbered `number` and up, oldest first.
+    async fn find_revisions_since(&self, id: EventId, number: i64) -> Result<Vec<Revision>, EventRepoError>;
+
+    /// Deletes events archived before `archived_before` with all their relationships,
+    /// returning how many were deleted. Their revisions are kept as an audit trail.
+    async fn purge_archived(&self, archived_before: DateTime<FixedOffset>) -> Result<u64, EventRepoError>;
+
+    /// Writes only the changed properties and adds or removes only the changed keywords,
+    /// if the event is still at `version`. Bumps the version and records the edit as an
+    /// `action` revision.
+    async fn edit(
+        &self,
+        id: EventId,
+        version: i64,
+        changes: &EventChanges,
+        action: RevisionAction,
+        user: &str,
+    ) -> Result<Event, EventRepoError>;
+
+    /// Up to `limit` upcoming events, those pinned by an admin first, then by the number
+    /// of places taken since `registered_since` and by attendee count.
+    async fn get_featured(
+        &self,
+        registered_since: DateTime<FixedOffset>,
+        limit: u32,
+    ) -> Result<Vec<Event>, EventRepoError>;
+
+    /// Pins the event to the featured events until `until`, or unpins it when `None`.
+    /// Archived events are not found.
+    async fn set_featured_until(
+        &self,
+        id: EventId,
+        until: Option<DateTime<FixedOffset>>,
+    ) -> Result<Event, EventRepoError>;
+
+    /// One page of the events passing both filters and their total. Events matching
+    /// more of the requested keywords come first, then the order of `filter` applies.
+    async fn get_events_by_keywords(
+        &self,
+        keywords: &KeywordFilter,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError>;
+
+    /// Keywords of events that aren't archived, those left only on archived events would
+    /// filter out everything.
+    async fn get_events_keywords(&self) -> Result<Vec<String>, EventRepoError>;
+
+    /// One page of the keywords of events that aren't archived with their event and
+    /// registration counts, and their total.
+    async fn get_keyword_stats(
+        &self,
+        sort: KeywordSort,
+        order: SortOrder,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<KeywordStats>, u32), EventRepoError>;
+
+    /// One page of the events whose name or keywords match the search terms, allowing
+    /// for typos, best matches first, and their total.
+    async fn search(
+        &self,
+        terms: &[String],
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<EventSearchHit>, u32), EventRepoError>;
+}
+
+pub struct Neo4jEventRepository {
     graph: Arc<Graph>,
 }
 
-impl EventRepository {
+impl Neo4jEventRepository {
     pub fn new(graph: Arc<Graph>) -> Self {
         Self { graph }
     }
+}
 
-    pub async fn find_by_id(&self, id: u16) -> Result<Event, EventRepoError> {
+#[rocket::async_trait]
+impl EventRepository for Neo4jEventRepository {
+    async fn find_by_id(&self, id: EventId) -> Result<Event, EventRepoError> {
         let mut result = self
             .graph
             .execute(
-                query(
+                event_query(
                     "\
                     MATCH (e:Event {id: $id})
                     OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
                     RETURN
-                        e.id               AS eventId,
-                        e.name             AS eventName,
-                        e.startDatetime    AS start,
+                        {event_columns},
                         collect(k.name)    AS keywords;",
                 )
                 .param("id", id),
@@ -64,19 +380,38 @@ impl EventRepository {
         }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<Event>, EventRepoError> {
+    async fn find_all(
+        &self,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+
+        let total = count(
+            &self.graph,
+            filtered_event_query("MATCH (e:Event) WHERE {event_filter} RETURN count(e) AS total", filter),
+        )
+        .await?;
+
         let mut result = self
             .graph
-            .execute(query(
-                "
+            .execute(
+                filtered_event_query(
+                    "
                     MATCH (e:Event)
+                    WHERE {event_filter}
                     OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
                     RETURN
-                        e.id               AS eventId,
-                        e.name             AS eventName,
-                        e.startDatetime    AS start,
-                        collect(k.name)    AS keywords;",
-            ))
+                        {event_columns},
+                        collect(k.name)    AS keywords
+                    ORDER BY {event_order}
+                    SKIP $skip LIMIT $limit;",
+                    filter,
+                )
+                .param("skip", skip)
+                .param("limit", limit as i64),
+            )
             .await?;
 
         let mut events_list: Vec<Event> = Vec::new();
@@ -91,142 +426,383 @@ impl EventRepository {
             }
         }
 
-        Ok(events_list)
+        Ok((events_list, total))
     }
 
-    pub async fn add(&self, event: EventUpdate) -> Result<Event, EventRepoError> {
+    async fn add(&self, event: EventUpdate, organizer: &str) -> Result<Event, EventRepoError> {
         if let Err(msg) = event.validate() {
             return Err(EventRepoError::Other(msg));
         }
+        let start_datetime = event.start().map_err(EventRepoError::Other)?;
+        let end_datetime = event.end().map_err(EventRepoError::Other)?;
 
-        let mut result = self
-            .graph
-            .execute(
-                query(
+        let mut txn = self.graph.start_txn().await?;
+        let row = first_row(
+            &mut txn,
+            event_query(
+                "\
+            // Take the next ID from the counter node. Setting the lock property first
+            // makes concurrent creates wait for each other instead of reading the same value.
+            // The counter starts from the highest imported ID.
+            MERGE (c:IdCounter {name: 'Event'})
+              ON CREATE SET c.value = coalesce(COLLECT { MATCH (e:Event) RETURN max(e.id) }[0], 0)
+            SET c._lock = true
+            SET c.value = c.value + 1
+            REMOVE c._lock
+            WITH c.value AS newId
+
+            // Create the event with the new ID, organized by the caller
+            MATCH (u:User {name: $organizer})
+            CREATE (e:Event {
+                id: newId,
+                name: $eventName,
+                startDatetime: datetime($startDatetime),
+                endDatetime: datetime($endDatetime),
+                timezone: $timezone,
+                capacity: $capacity,
+                version: 1
+            })
+            CREATE (u)-[:ORGANIZES {primary: true}]->(e)
+
+            // Add keywords
+            WITH e
+            UNWIND $keywords AS kw
+              MERGE (k:EventKeyword { name: kw })
+              MERGE (e)-[:HAS]->(k)
+
+            // Return the created event
+            RETURN
+               {event_columns},
+               collect(k.name)    AS keywords
+            ",
+            )
+            .param("organizer", organizer)
+            .param("eventName", event.name)
+            .param("startDatetime", start_datetime.to_rfc3339())
+            .param("endDatetime", end_datetime.map(|end| end.to_rfc3339()))
+            .param("timezone", event.timezone)
+            .param("capacity", event.capacity)
+            .param("keywords", unique_keywords(event.keywords)),
+        )
+        .await;
+
+        let result = async {
+            let event = match row? {
+                Some(row) => Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))?,
+                None => return Err(EventRepoError::Other("Can't create event".to_string())),
+            };
+            add_revision(&mut txn, RevisionAction::Created, organizer, None, &event).await?;
+            Ok(event)
+        }
+        .await;
+
+        finish(txn, result).await
+    }
+
+    async fn remove(&self, id: EventId, user: &str) -> Result<String, EventRepoError> {
+        let mut txn = self.graph.start_txn().await?;
+        let result = async {
+            let before = match lock_event(&mut txn, id).await? {
+                Some(event) if event.archived_at.is_none() => event,
+                _ => return Err(EventRepoError::NotFound(id)),
+            };
+
+            let row = first_row(
+                &mut txn,
+                event_query(
+                    "\
+                    MATCH (e:Event {id: $id})
+                    SET e.archivedAt = datetime()
+                    REMOVE e.featuredUntil
+                    WITH e
+                    OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                    RETURN
+                        {event_columns},
+                        collect(k.name)    AS keywords;",
+                )
+                .param("id", id),
+            )
+            .await?
+            .ok_or(EventRepoError::NotFound(id))?;
+            let after = Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))?;
+
+            add_revision(&mut txn, RevisionAction::Deleted, user, Some(&before), &after).await?;
+            Ok(format!("Event with id {} archived", id))
+        }
+        .await;
+
+        finish(txn, result).await
+    }
+
+    async fn restore(&self, id: EventId, user: &str) -> Result<Event, EventRepoError> {
+        let mut txn = self.graph.start_txn().await?;
+        let result = async {
+            let before = match lock_event(&mut txn, id).await? {
+                Some(event) if event.archived_at.is_some() => event,
+                Some(_) => return Err(EventRepoError::NotArchived(id)),
+                None => return Err(EventRepoError::NotFound(id)),
+            };
+
+            let row = first_row(
+                &mut txn,
+                event_query(
                     "\
-                // Find the max ID and increment by 1 for the new event
+                    MATCH (e:Event {id: $id})
+                    REMOVE e.archivedAt
+                    WITH e
+                    OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                    RETURN
+                        {event_columns},
+                        collect(k.name)    AS keywords;",
+                )
+                .param("id", id),
+            )
+            .await?
+            .ok_or(EventRepoError::NotFound(id))?;
+            let after = Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))?;
+
+            add_revision(&mut txn, RevisionAction::Restored, user, Some(&before), &after).await?;
+            Ok(after)
+        }
+        .await;
+
+        finish(txn, result).await
+    }
+
+    async fn find_archived(
+        &self,
+        organizer: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+
+        let matching = "
                 MATCH (e:Event)
-                WITH COALESCE(MAX(e.id), 0) + 1 AS newId
-
-                // Create the event with the new ID
-                CREATE (e:Event {
-                    id: newId,
-                    name: $eventName,
-                    startDatetime: datetime($startDatetime)
-                })
-
-                // Add keywords
-                WITH e
-                UNWIND $keywords AS kw
-                  MERGE (k:EventKeyword { name: kw })
-                  MERGE (e)-[:HAS]->(k)
-
-                // Return the created event
+                WHERE e.archivedAt IS NOT NULL
+                  AND ($organizer IS NULL OR EXISTS { (:User {name: $organizer})-[:ORGANIZES]->(e) })";
+
+        let total = count(
+            &self.graph,
+            query(&format!("{matching} RETURN count(e) AS total")).param("organizer", organizer),
+        )
+        .await?;
+
+        let mut rows = self
+            .graph
+            .execute(
+                event_query(&format!(
+                    "{matching}
+                OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
                 RETURN
-                   e.id               AS eventId,
-                   e.name             AS eventName,
-                   e.startDatetime    AS start,
-                   collect(k.name)    AS keywords
-                ",
-                )
-                .param("eventName", event.name)
-                .param("startDatetime", event.start_datetime)
-                .param("keywords", event.keywords),
+                    {{event_columns}},
+                    collect(k.name)    AS keywords
+                ORDER BY archivedAt DESC, eventId
+                SKIP $skip LIMIT $limit;"
+                ))
+                .param("organizer", organizer)
+                .param("skip", skip)
+                .param("limit", limit as i64),
             )
             .await?;
 
-        match result.next().await? {
-            Some(row) => {
-                Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))
-            }
-            None => Err(EventRepoError::Other("Can't create event".to_string())),
+        let mut events = Vec::<Event>::new();
+        while let Some(row) = rows.next().await? {
+            events.push(Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))?);
         }
+        Ok((events, total))
     }
 
-    pub async fn remove(&self, id: u16) -> Result<String, EventRepoError> {
-        let exists = self.find_by_id(id).await.is_ok();
-        if !exists {
-            return Err(EventRepoError::NotFound(id));
+    async fn find_revisions(&self, id: EventId, page: u32, limit: u32) -> Result<(Vec<Revision>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+
+        let total = count(
+            &self.graph,
+            query("MATCH (:Event { id: $eventId })-[:HAS_REVISION]->(r:EventRevision) RETURN count(r) AS total")
+                .param("eventId", id),
+        )
+        .await?;
+
+        let mut rows = self
+            .graph
+            .execute(
+                query(&format!(
+                    "\
+                MATCH (:Event {{ id: $eventId }})-[:HAS_REVISION]->(r:EventRevision)
+                RETURN {REVISION_COLUMNS}
+                ORDER BY number DESC
+                SKIP $skip LIMIT $limit;
+                "
+                ))
+                .param("eventId", id)
+                .param("skip", skip)
+                .param("limit", limit as i64),
+            )
+            .await?;
+
+        let mut revisions = Vec::<Revision>::new();
+        while let Some(row) = rows.next().await? {
+            revisions.push(revision_from_row(&row)?);
+        }
+        Ok((revisions, total))
+    }
+
+    async fn find_revisions_since(&self, id: EventId, number: i64) -> Result<Vec<Revision>, EventRepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                query(&format!(
+                    "\
+                MATCH (:Event {{ id: $eventId }})-[:HAS_REVISION]->(r:EventRevision)
+                WHERE r.number >= $number
+                RETURN {REVISION_COLUMNS}
+                ORDER BY number;
+                "
+                ))
+                .param("eventId", id)
+                .param("number", number),
+            )
+            .await?;
+
+        let mut revisions = Vec::<Revision>::new();
+        while let Some(row) = rows.next().await? {
+            revisions.push(revision_from_row(&row)?);
         }
+        Ok(revisions)
+    }
 
-        let _ = self
+    async fn purge_archived(&self, archived_before: DateTime<FixedOffset>) -> Result<u64, EventRepoError> {
+        let row = self
             .graph
             .execute(
                 query(
                     "\
-                MATCH (e:Event { id: $eventId })
-                DETACH DELETE e;
+                MATCH (e:Event)
+                WHERE e.archivedAt < datetime($archivedBefore)
+                // Revisions keep the event ID, they stay behind once HAS_REVISION is gone
+                WITH collect(e) AS archived
+                FOREACH (e IN archived | DETACH DELETE e)
+                RETURN size(archived) AS purged;
                 ",
                 )
-                .param("eventId", id),
+                .param("archivedBefore", archived_before.to_rfc3339()),
             )
             .await?
             .next()
             .await?;
 
-        Ok(format!("Event with id {} successfully deleted", id))
+        Ok(row.and_then(|row| row.get::<i64>("purged").ok()).unwrap_or(0) as u64)
     }
 
-    pub async fn edit(&self, id: u16, event_update: EventUpdate) -> Result<Event, EventRepoError> {
-        if let Err(msg) = event_update.validate() {
-            return Err(EventRepoError::Other(msg));
+    async fn edit(
+        &self,
+        id: EventId,
+        version: i64,
+        changes: &EventChanges,
+        action: RevisionAction,
+        user: &str,
+    ) -> Result<Event, EventRepoError> {
+        let mut properties = vec!["e.version = coalesce(e.version, 0) + 1"];
+        if changes.name.is_some() {
+            properties.push("e.name = $eventName");
         }
-
-        let exists = self.find_by_id(id).await.is_ok();
-        if !exists {
-            return Err(EventRepoError::NotFound(id));
+        if changes.start_datetime.is_some() {
+            properties.push("e.startDatetime = datetime($startDatetime)");
         }
+        if changes.end_datetime.is_some() {
+            properties.push("e.endDatetime = datetime($endDatetime)");
+        }
+        if changes.timezone.is_some() {
+            properties.push("e.timezone = $timezone");
+        }
+        if changes.capacity.is_some() {
+            properties.push("e.capacity = $capacity");
+        }
+        let set = properties.join(", ");
+        // A raised capacity frees places for the waitlist
+        let promote = if changes.capacity.is_some() { PROMOTE_ALL_FROM_WAITLIST } else { "" };
 
-        let mut result = self
-            .graph
-            .execute(
-                query(
+        let mut txn = self.graph.start_txn().await?;
+        let result = async {
+            // Locked before the version is compared, so a concurrent edit can't slip in between
+            let before = match lock_event(&mut txn, id).await? {
+                Some(event) if event.version == version => event,
+                Some(_) => return Err(EventRepoError::VersionMismatch(id)),
+                None => return Err(EventRepoError::NotFound(id)),
+            };
+
+            let row = first_row(
+                &mut txn,
+                event_query(&format!(
                     "\
-            MATCH (e:Event { id: $eventId })
-            SET
-              e.name          = coalesce($eventName, e.name),
-              e.startDatetime = coalesce(datetime($startDatetime), e.startDatetime)
+            MATCH (e:Event {{ id: $eventId }})
+            SET {set}
+            {promote}
             WITH e
-            OPTIONAL MATCH (e)-[oldRel:HAS]->(oldK:EventKeyword)
-            DELETE oldRel
-            WITH e
-            UNWIND $keywords AS kw
-              MERGE (k:EventKeyword { name: kw })
+            OPTIONAL MATCH (e)-[removed:HAS]->(removedK:EventKeyword)
+            WHERE removedK.name IN $removedKeywords
+            DELETE removed
+            WITH DISTINCT e
+            FOREACH (kw IN $addedKeywords |
+              MERGE (k:EventKeyword {{ name: kw }})
               MERGE (e)-[:HAS]->(k)
+            )
+            WITH e
+            OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
             RETURN
-               e.id               AS eventId,
-               e.name             AS eventName,
-               e.startDatetime    AS start,
-               collect(k.name)    AS keywords;",
-                )
+               {{event_columns}},
+               collect(k.name)    AS keywords;"
+                ))
                 .param("eventId", id)
-                .param("eventName", event_update.name)
-                .param("startDatetime", event_update.start_datetime)
-                .param("keywords", event_update.keywords),
+                .param("eventName", changes.name.clone())
+                .param("startDatetime", changes.start_datetime.map(|start| start.to_rfc3339()))
+                .param("endDatetime", changes.end_datetime.flatten().map(|end| end.to_rfc3339()))
+                .param("timezone", changes.timezone.clone().flatten())
+                .param("capacity", changes.capacity.flatten())
+                .param("removedKeywords", changes.removed_keywords.clone())
+                .param("addedKeywords", changes.added_keywords.clone()),
             )
-            .await?;
+            .await?
+            .ok_or(EventRepoError::NotFound(id))?;
+            let after = Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))?;
 
-        match result.next().await? {
-            Some(row) => {
-                Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))
-            }
-            None => Err(EventRepoError::NotFound(id)),
+            add_revision(&mut txn, action, user, Some(&before), &after).await?;
+            Ok(after)
         }
+        .await;
+
+        finish(txn, result).await
     }
 
-    pub async fn get_featured(&self) -> Result<Vec<Event>, EventRepoError> {
+    async fn get_featured(
+        &self,
+        registered_since: DateTime<FixedOffset>,
+        limit: u32,
+    ) -> Result<Vec<Event>, EventRepoError> {
         let mut result = self
             .graph
-            .execute(query(
-                "MATCH (e:Event)
+            .execute(
+                event_query(
+                    "MATCH (e:Event)
+                    WHERE e.startDatetime > datetime() AND e.archivedAt IS NULL
                     OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                    WITH e, collect(k.name) AS keywords
                     RETURN
-                        e.id               AS eventId,
-                        e.name             AS eventName,
-                        e.startDatetime    AS start,
-                        collect(k.name)    AS keywords
-                    LIMIT 3;",
-            ))
+                        {event_columns},
+                        keywords,
+                        coalesce(e.featuredUntil > datetime(), false) AS pinned,
+                        COUNT {
+                          (:User)-[r:REGISTERED_TO]->(e)
+                          WHERE coalesce(r.state, 'going') IN $positiveStates
+                            AND r.registeredAt >= datetime($registeredSince)
+                        } AS recentRegistrations
+                    ORDER BY pinned DESC, recentRegistrations DESC, attendeeCount DESC, start, eventId
+                    LIMIT $limit;",
+                )
+                .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
+                .param("registeredSince", registered_since.to_rfc3339())
+                .param("limit", limit as i64),
+            )
             .await?;
 
         let mut events_list: Vec<Event> = Vec::new();
@@ -244,19 +820,85 @@ impl EventRepository {
         Ok(events_list)
     }
 
-    pub async fn get_events_by_keywords(&self, keyword: Vec<String>) -> Result<Vec<Event>, EventRepoError> {
+    async fn set_featured_until(
+        &self,
+        id: EventId,
+        until: Option<DateTime<FixedOffset>>,
+    ) -> Result<Event, EventRepoError> {
+        let mut result = self
+            .graph
+            .execute(
+                event_query(
+                    "\
+                    MATCH (e:Event {id: $id})
+                    WHERE e.archivedAt IS NULL
+                    SET e.featuredUntil = datetime($featuredUntil)
+                    WITH e
+                    OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                    RETURN
+                        {event_columns},
+                        collect(k.name)    AS keywords;",
+                )
+                .param("id", id)
+                .param("featuredUntil", until.map(|until| until.to_rfc3339())),
+            )
+            .await?;
+
+        match result.next().await? {
+            Some(row) => {
+                Event::from_row(&row).map_err(|e| EventRepoError::ParseError(e.to_string()))
+            }
+            None => Err(EventRepoError::NotFound(id)),
+        }
+    }
+
+    async fn get_events_by_keywords(
+        &self,
+        keywords: &KeywordFilter,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+
+        // Binds the events passing both filters to `e`, with their `keywords` and
+        // the number of `matched` requested keywords
+        let matching = r#"
+                MATCH (e:Event)
+                WHERE {event_filter}
+                WITH e, [(e)-[:HAS]->(k:EventKeyword) | k.name] AS keywords
+                WITH e, keywords, size([kw IN $kws WHERE kw IN keywords]) AS matched
+                WHERE size(keywords) > 0
+                  AND none(kw IN keywords WHERE kw IN $excluded)
+                  AND CASE
+                        WHEN $matchAll THEN matched = size($kws)
+                        ELSE size($kws) = 0 OR matched > 0
+                      END"#;
+
+        let total = count(
+            &self.graph,
+            filtered_event_query(&format!("{matching} RETURN count(e) AS total"), filter)
+                .param("kws", keywords.keywords.clone())
+                .param("excluded", keywords.excluded.clone())
+                .param("matchAll", keywords.mode == KeywordMatch::All),
+        )
+        .await
+        .map_err(|e| Other(e.to_string()))?;
+
         let mut rows = self.graph.execute(
-            query(r#"
-                MATCH (e:Event)-[:HAS]->(k:EventKeyword)
-                WITH e, COLLECT(k.name) AS keywords
-                WHERE all(kw IN $kws WHERE kw IN keywords)
+            filtered_event_query(&format!(r#"
+                {matching}
                 RETURN
-                    e.id               AS eventId,
-                    e.name             AS eventName,
-                    e.startDatetime    AS start,
+                    {{event_columns}},
                     keywords
-            "#)
-                .param("kws", keyword)
+                ORDER BY matched DESC, {{event_order}}
+                SKIP $skip LIMIT $limit
+            "#), filter)
+                .param("kws", keywords.keywords.clone())
+                .param("excluded", keywords.excluded.clone())
+                .param("matchAll", keywords.mode == KeywordMatch::All)
+                .param("skip", skip)
+                .param("limit", limit as i64)
         ).await.map_err(|e| Other(e.to_string()))?;
         let mut events = Vec::<Event>::new();
         while let Some(row) = match rows.next().await {
@@ -266,13 +908,14 @@ impl EventRepository {
             let event: Event = Event::from_row(&row).map_err(|e|Other(e.to_string()))?;
             events.push(event);
         }
-        Ok(events)
+        Ok((events, total))
     }
 
-    pub async fn get_events_keywords(&self) -> Result<Vec<String>, EventRepoError> {
+    async fn get_events_keywords(&self) -> Result<Vec<String>, EventRepoError> {
         let mut rows = self.graph.execute(
             query(r#"
             MATCH (k:EventKeyword)
+            WHERE EXISTS { (k)<-[:HAS]-(e:Event) WHERE e.archivedAt IS NULL }
             RETURN k.name
             "#
             )
@@ -287,4 +930,131 @@ impl EventRepository {
         }
         Ok(keywords)
     }
+
+    async fn get_keyword_stats(
+        &self,
+        sort: KeywordSort,
+        order: SortOrder,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<KeywordStats>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+        let direction = match order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let order_by = match sort {
+            KeywordSort::Events => format!("eventCount {}, name", direction),
+            KeywordSort::Upcoming => format!("upcomingEventCount {}, name", direction),
+            KeywordSort::Registrations => format!("registrationCount {}, name", direction),
+            KeywordSort::Name => format!("name {}", direction),
+        };
+
+        let total = count(
+            &self.graph,
+            query(
+                "MATCH (k:EventKeyword)
+                 WHERE EXISTS { (k)<-[:HAS]-(e:Event) WHERE e.archivedAt IS NULL }
+                 RETURN count(k) AS total",
+            ),
+        )
+        .await
+            .map_err(|e| Other(e.to_string()))?;
+
+        let mut rows = self.graph.execute(
+            query(&format!(r#"
+                MATCH (k:EventKeyword)<-[:HAS]-(e:Event WHERE e.archivedAt IS NULL)
+                WITH k, e,
+                     COUNT {{
+                       (:User)-[r:REGISTERED_TO]->(e) WHERE coalesce(r.state, 'going') IN $positiveStates
+                     }} AS registrations
+                WITH k,
+                     count(e) AS eventCount,
+                     count(CASE WHEN e.startDatetime > datetime() THEN e END) AS upcomingEventCount,
+                     sum(registrations) AS registrationCount
+                RETURN k.name AS name, eventCount, upcomingEventCount, registrationCount
+                ORDER BY {}
+                SKIP $skip LIMIT $limit
+            "#, order_by))
+                .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
+                .param("skip", skip)
+                .param("limit", limit as i64)
+        ).await.map_err(|e| Other(e.to_string()))?;
+        let mut stats = Vec::<KeywordStats>::new();
+        while let Some(row) = match rows.next().await {
+            Ok(r) => r,
+            Err(e) => return Err(Other(e.to_string())),
+        } {
+            stats.push(KeywordStats {
+                name: row.get("name").map_err(|e| Other(e.to_string()))?,
+                event_count: row.get("eventCount").map_err(|e| Other(e.to_string()))?,
+                upcoming_event_count: row.get("upcomingEventCount").map_err(|e| Other(e.to_string()))?,
+                registration_count: row.get("registrationCount").map_err(|e| Other(e.to_string()))?,
+            });
+        }
+        Ok((stats, total))
+    }
+
+    async fn search(
+        &self,
+        terms: &[String],
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<EventSearchHit>, u32), EventRepoError> {
+        let skip = skip(page, limit);
+
+        // Terms are plain alphanumeric words, so they need no escaping in Lucene syntax
+        let lucene_query = terms
+            .iter()
+            .map(|term| match search_fuzziness(term) {
+                0 => term.clone(),
+                edits => format!("{}~{}", term, edits),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // Binds the matching events to `e`, each with its summed `score`
+        let matching = "
+                CALL db.index.fulltext.queryNodes('event_search', $query) YIELD node, score
+
+                // Keyword hits count for every event having the keyword
+                OPTIONAL MATCH (node:EventKeyword)<-[:HAS]-(tagged:Event)
+                WITH CASE WHEN node:Event THEN node ELSE tagged END AS e, score
+                WHERE e IS NOT NULL AND e.archivedAt IS NULL
+                WITH e, sum(score) AS score";
+
+        let total = count(
+            &self.graph,
+            query(&format!("{matching} RETURN count(e) AS total")).param("query", lucene_query.clone()),
+        )
+        .await
+        .map_err(|e| Other(e.to_string()))?;
+
+        let mut rows = self.graph.execute(
+            event_query(&format!(r#"
+                {matching}
+                ORDER BY score DESC, e.id
+                SKIP $skip LIMIT $limit
+                OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                RETURN
+                    {{event_columns}},
+                    collect(k.name)    AS keywords,
+                    score
+                ORDER BY score DESC, eventId
+            "#))
+                .param("query", lucene_query)
+                .param("skip", skip)
+                .param("limit", limit as i64)
+        ).await.map_err(|e| Other(e.to_string()))?;
+        let mut hits = Vec::<EventSearchHit>::new();
+        while let Some(row) = match rows.next().await {
+            Ok(r) => r,
+            Err(e) => return Err(Other(e.to_string())),
+        } {
+            let event: Event = Event::from_row(&row).map_err(|e|Other(e.to_string()))?;
+            let score: f64 = row.get("score").map_err(|e|Other(e.to_string()))?;
+            hits.push(EventSearchHit { event, score });
+        }
+        Ok((hits, total))
+    }
 }
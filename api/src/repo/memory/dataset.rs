@@ -0,0 +1,191 @@
+use crate::db::memory::{EventNode, GraphData, MemoryGraph, RegistrationEdge};
+use crate::models::dataset::{EventKeywordRow, EventRecord, GraphDump, RegistrationRecord, SimilarityRecord, UserEventRow};
+use crate::models::event::EventId;
+use crate::models::registration::RsvpState;
+use crate::repo::RepoError;
+use crate::repo::dataset::DatasetRepository;
+use std::sync::Arc;
+
+pub struct InMemoryDatasetRepository {
+    graph: Arc<MemoryGraph>,
+}
+
+impl InMemoryDatasetRepository {
+    pub fn new(graph: Arc<MemoryGraph>) -> Self {
+        Self { graph }
+    }
+}
+
+#[rocket::async_trait]
+impl DatasetRepository for InMemoryDatasetRepository {
+    async fn clear(&self) -> Result<(), RepoError> {
+        *self.graph.write() = GraphData::default();
+        Ok(())
+    }
+
+    async fn is_empty(&self) -> Result<bool, RepoError> {
+        let data = self.graph.read();
+        Ok(data.users.is_empty() && data.events.is_empty() && data.keywords.is_empty())
+    }
+
+    async fn import_events(&self, rows: &[EventRecord]) -> Result<(), RepoError> {
+        let mut data = self.graph.write();
+        for row in rows {
+            data.events.entry(row.id).or_insert_with(|| EventNode {
+                name: row.name.clone(),
+                start_datetime: row.start_datetime,
+                end_datetime: row.end_datetime,
+                timezone: row.timezone.clone(),
+                capacity: row.capacity,
+                keywords: Vec::new(),
+                featured_until: None,
+                version: 1,
+                archived_at: row.archived_at,
+                waitlist_counter: 0,
+            });
+        }
+        Ok(())
+    }
+
+    async fn import_user_events(&self, rows: &[UserEventRow]) -> Result<(), RepoError> {
+        let mut data = self.graph.write();
+        for row in rows {
+            data.users.entry(row.user_name.clone()).or_default();
+            data.events.entry(row.event_id).or_insert_with(|| EventNode {
+                name: row.event_name.clone(),
+                start_datetime: row.start_datetime,
+                end_datetime: None,
+                timezone: None,
+                capacity: None,
+                keywords: Vec::new(),
+                featured_until: None,
+                version: 1,
+                archived_at: None,
+                waitlist_counter: 0,
+            });
+            data.registrations
+                .entry((row.user_name.clone(), row.event_id))
+                .or_insert_with(|| RegistrationEdge::new(RsvpState::Going));
+        }
+        Ok(())
+    }
+
+    async fn import_event_keywords(&self, rows: &[EventKeywordRow]) -> Result<Vec<EventId>, RepoError> {
+        let mut data = self.graph.write();
+        let mut unknown = Vec::new();
+        for row in rows {
+            let Some(node) = data.events.get_mut(&row.event_id) else {
+                if !unknown.contains(&row.event_id) {
+                    unknown.push(row.event_id);
+                }
+                continue;
+            };
+            if !node.keywords.contains(&row.keyword) {
+                node.keywords.push(row.keyword.clone());
+            }
+            data.keywords.insert(row.keyword.clone());
+        }
+        Ok(unknown)
+    }
+
+    async fn sync_event_id_counter(&self) -> Result<(), RepoError> {
+        let mut data = self.graph.write();
+        let max_id = data.events.keys().next_back().copied().unwrap_or(0);
+        data.event_id_counter = data.event_id_counter.max(max_id);
+        Ok(())
+    }
+
+    async fn dump(&self) -> Result<GraphDump, RepoError> {
+        let data = self.graph.read();
+        let mut event_keywords: Vec<EventKeywordRow> = data
+            .events
+            .iter()
+            .flat_map(|(id, node)| {
+                node.keywords.iter().map(|keyword| EventKeywordRow {
+                    event_id: *id,
+                    keyword: keyword.clone(),
+                })
+            })
+            .collect();
+        event_keywords.sort_by(|a, b| (a.event_id, &a.keyword).cmp(&(b.event_id, &b.keyword)));
+
+        Ok(GraphDump {
+            users: data.users.iter().map(|(name, node)| node.to_user(name)).collect(),
+            events: data
+                .events
+                .iter()
+                .map(|(id, node)| EventRecord {
+                    id: *id,
+                    name: node.name.clone(),
+                    start_datetime: node.start_datetime,
+                    end_datetime: node.end_datetime,
+                    timezone: node.timezone.clone(),
+                    capacity: node.capacity,
+                    archived_at: node.archived_at,
+                })
+                .collect(),
+            keywords: data.keywords.iter().cloned().collect(),
+            registrations: data
+                .registrations
+                .iter()
+                .map(|((user_name, event_id), edge)| RegistrationRecord {
+                    user_name: user_name.clone(),
+                    event_id: *event_id,
+                    state: edge.state,
+                    registered_at: Some(edge.registered_at),
+                })
+                .collect(),
+            event_keywords,
+            // Similarity scores only exist in Neo4j, where the GDS pipeline writes them.
+            similar: data
+                .similar
+                .iter()
+                .map(|(user_name, similar_user_name)| SimilarityRecord {
+                    user_name: user_name.clone(),
+                    similar_user_name: similar_user_name.clone(),
+                    score: None,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryConnection;
+
+    fn user_event(user_name: &str, event_id: EventId) -> UserEventRow {
+        UserEventRow::parse(&[user_name, &event_id.to_string(), "Event", "2099-01-01T10:00:00Z"]).unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn import_merges_and_dumps() {
+        let repo = InMemoryDatasetRepository::new(MemoryConnection::new().graph);
+        repo.import_user_events(&[user_event("b", 2), user_event("a", 2), user_event("a", 2)])
+            .await
+            .unwrap();
+        let unknown = repo
+            .import_event_keywords(&[
+                EventKeywordRow { event_id: 2, keyword: "music".to_string() },
+                EventKeywordRow { event_id: 7, keyword: "sport".to_string() },
+            ])
+            .await
+            .unwrap();
+        assert_eq!(unknown, [7]);
+
+        repo.sync_event_id_counter().await.unwrap();
+        assert_eq!(repo.graph.read().event_id_counter, 2);
+
+        let dump = repo.dump().await.unwrap();
+        assert_eq!(dump.users.iter().map(|user| user.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(dump.events.len(), 1);
+        assert_eq!(dump.registrations.len(), 2);
+        assert_eq!(dump.keywords, ["music"]);
+        assert_eq!(dump.event_keywords.len(), 1);
+
+        assert!(!repo.is_empty().await.unwrap());
+        repo.clear().await.unwrap();
+        assert!(repo.is_empty().await.unwrap());
+    }
+}
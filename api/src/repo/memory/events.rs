@@ -0,0 +1,706 @@
+use crate::db::memory::{EventNode, GraphData, MemoryGraph};
+use crate::models::event::{
+    Event, EventChanges, EventFilter, EventId, EventSearchHit, EventSort, EventUpdate, KeywordFilter, SortOrder, search_fuzziness,
+    search_terms,
+};
+use crate::models::keyword::{KeywordSort, KeywordStats};
+use crate::models::revision::{self, Revision, RevisionAction};
+use crate::repo::events::{EventRepoError, EventRepository, unique_keywords};
+use crate::repo::memory::users_events::promote_from_waitlist;
+use crate::utils::pagination::skip;
+use chrono::{DateTime, FixedOffset, Utc};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+pub struct InMemoryEventRepository {
+    graph: Arc<MemoryGraph>,
+}
+
+impl InMemoryEventRepository {
+    pub fn new(graph: Arc<MemoryGraph>) -> Self {
+        Self { graph }
+    }
+}
+
+/// Orders events like the `{event_order}` of `filtered_event_query`.
+fn compare_events(a: &Event, b: &Event, filter: &EventFilter) -> Ordering {
+    let ordering = match filter.sort {
+        EventSort::Start => a.start_datetime.cmp(&b.start_datetime),
+        EventSort::Name => a.name.cmp(&b.name),
+        EventSort::Popularity => a.attendee_count.cmp(&b.attendee_count),
+    };
+    let ordering = match filter.order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+    };
+
+    let tie_break = match filter.sort {
+        EventSort::Popularity => a.start_datetime.cmp(&b.start_datetime),
+        EventSort::Start | EventSort::Name => Ordering::Equal,
+    };
+    ordering.then(tie_break).then(a.id.cmp(&b.id))
+}
+
+/// Number of single character edits turning `a` into `b`, counting swapped
+/// neighbours as one edit like Lucene's fuzzy queries.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// A rough stand-in for the Lucene score of the full-text index: every search term
+/// adds up to 1 for the closest word of `text` within its allowed typos.
+fn search_score(terms: &[String], text: &str) -> f64 {
+    let words = search_terms(text);
+    terms
+        .iter()
+        .filter_map(|term| {
+            words
+                .iter()
+                .map(|word| edit_distance(term, word))
+                .filter(|distance| *distance <= search_fuzziness(term))
+                .min()
+                .map(|distance| 1.0 / (1 + distance) as f64)
+        })
+        .sum()
+}
+
+/// Appends the revision taking the event from `before` to `after` to its history,
+/// numbered after the last one.
+fn add_revision(data: &mut GraphData, action: RevisionAction, user: &str, before: Option<&Event>, after: &Event) {
+    let revisions = data.revisions.entry(after.id).or_default();
+    revisions.push(Revision {
+        number: revisions.len() as i64 + 1,
+        action,
+        user: user.to_string(),
+        timestamp: Utc::now().fixed_offset(),
+        changes: revision::diff(before, after),
+    });
+}
+
+#[rocket::async_trait]
+impl EventRepository for InMemoryEventRepository {
+    async fn find_by_id(&self, id: EventId) -> Result<Event, EventRepoError> {
+        let data = self.graph.read();
+        data.event(id).ok_or(EventRepoError::NotFound(id))
+    }
+
+    async fn find_all(
+        &self,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let mut events: Vec<Event> = data
+            .active_events()
+            .filter(|(_, node)| filter.matches(node.start_datetime))
+            .map(|(id, node)| data.to_event(*id, node))
+            .collect();
+        events.sort_by(|a, b| compare_events(a, b, filter));
+
+        let total = events.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((events.into_iter().skip(skip).take(limit as usize).collect(), total))
+    }
+
+    async fn add(&self, event: EventUpdate, organizer: &str) -> Result<Event, EventRepoError> {
+        if let Err(msg) = event.validate() {
+            return Err(EventRepoError::Other(msg));
+        }
+
+        let start_datetime = event.start().map_err(EventRepoError::Other)?;
+        let end_datetime = event.end().map_err(EventRepoError::Other)?;
+
+        let mut data = self.graph.write();
+        if !data.users.contains_key(organizer) {
+            return Err(EventRepoError::Other("Can't create event".to_string()));
+        }
+
+        let max_id = data.events.keys().next_back().copied().unwrap_or(0);
+        let new_id = data
+            .event_id_counter
+            .max(max_id)
+            .checked_add(1)
+            .ok_or_else(|| EventRepoError::Other("Can't create event".to_string()))?;
+        data.event_id_counter = new_id;
+
+        let keywords = unique_keywords(event.keywords);
+        data.keywords.extend(keywords.iter().cloned());
+
+        let node = EventNode {
+            name: event.name,
+            start_datetime,
+            end_datetime,
+            timezone: event.timezone,
+            capacity: event.capacity,
+            keywords,
+            featured_until: None,
+            version: 1,
+            archived_at: None,
+            waitlist_counter: 0,
+        };
+        data.events.insert(new_id, node);
+        data.organizes.insert((organizer.to_string(), new_id), true);
+
+        let event = data.event(new_id).ok_or_else(|| EventRepoError::Other("Can't create event".to_string()))?;
+        add_revision(&mut data, RevisionAction::Created, organizer, None, &event);
+        Ok(event)
+    }
+
+    async fn remove(&self, id: EventId, user: &str) -> Result<String, EventRepoError> {
+        let mut data = self.graph.write();
+        let before = data
+            .event(id)
+            .filter(|event| event.archived_at.is_none())
+            .ok_or(EventRepoError::NotFound(id))?;
+        if let Some(node) = data.events.get_mut(&id) {
+            node.archived_at = Some(Utc::now().fixed_offset());
+            node.featured_until = None;
+        }
+
+        let after = data.event(id).ok_or(EventRepoError::NotFound(id))?;
+        add_revision(&mut data, RevisionAction::Deleted, user, Some(&before), &after);
+        Ok(format!("Event with id {} archived", id))
+    }
+
+    async fn restore(&self, id: EventId, user: &str) -> Result<Event, EventRepoError> {
+        let mut data = self.graph.write();
+        let before = data.event(id).ok_or(EventRepoError::NotFound(id))?;
+        if before.archived_at.is_none() {
+            return Err(EventRepoError::NotArchived(id));
+        }
+        if let Some(node) = data.events.get_mut(&id) {
+            node.archived_at = None;
+        }
+
+        let after = data.event(id).ok_or(EventRepoError::NotFound(id))?;
+        add_revision(&mut data, RevisionAction::Restored, user, Some(&before), &after);
+        Ok(after)
+    }
+
+    async fn find_archived(
+        &self,
+        organizer: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let mut events: Vec<Event> = data
+            .events
+            .iter()
+            .filter(|(_, node)| node.archived_at.is_some())
+            .filter(|(id, _)| {
+                organizer.is_none_or(|organizer| data.organizes.contains_key(&(organizer.to_string(), **id)))
+            })
+            .map(|(id, node)| data.to_event(*id, node))
+            .collect();
+        events.sort_by(|a, b| b.archived_at.cmp(&a.archived_at).then(a.id.cmp(&b.id)));
+
+        let total = events.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((events.into_iter().skip(skip).take(limit as usize).collect(), total))
+    }
+
+    async fn find_revisions(&self, id: EventId, page: u32, limit: u32) -> Result<(Vec<Revision>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let revisions = data.revisions.get(&id).map(Vec::as_slice).unwrap_or_default();
+
+        let total = revisions.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((revisions.iter().rev().skip(skip).take(limit as usize).cloned().collect(), total))
+    }
+
+    async fn find_revisions_since(&self, id: EventId, number: i64) -> Result<Vec<Revision>, EventRepoError> {
+        let data = self.graph.read();
+        Ok(data
+            .revisions
+            .get(&id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|revision| revision.number >= number)
+            .cloned()
+            .collect())
+    }
+
+    async fn purge_archived(&self, archived_before: DateTime<FixedOffset>) -> Result<u64, EventRepoError> {
+        let mut data = self.graph.write();
+        let purged: Vec<EventId> = data
+            .events
+            .iter()
+            .filter(|(_, node)| node.archived_at.is_some_and(|archived_at| archived_at < archived_before))
+            .map(|(id, _)| *id)
+            .collect();
+
+        // Revisions are kept as an audit trail, event IDs are never reused
+        for id in &purged {
+            data.events.remove(id);
+        }
+        data.registrations.retain(|(_, event_id), _| !purged.contains(event_id));
+        data.waitlist.retain(|(_, event_id), _| !purged.contains(event_id));
+        data.organizes.retain(|(_, event_id), _| !purged.contains(event_id));
+
+        Ok(purged.len() as u64)
+    }
+
+    async fn edit(
+        &self,
+        id: EventId,
+        version: i64,
+        changes: &EventChanges,
+        action: RevisionAction,
+        user: &str,
+    ) -> Result<Event, EventRepoError> {
+        let mut data = self.graph.write();
+        let before = data.event(id).ok_or(EventRepoError::NotFound(id))?;
+        if before.version != version {
+            return Err(EventRepoError::VersionMismatch(id));
+        }
+
+        let node = data.events.get_mut(&id).ok_or(EventRepoError::NotFound(id))?;
+
+        node.version += 1;
+        if let Some(name) = &changes.name {
+            node.name = name.clone();
+        }
+        if let Some(start_datetime) = changes.start_datetime {
+            node.start_datetime = start_datetime;
+        }
+        if let Some(end_datetime) = changes.end_datetime {
+            node.end_datetime = end_datetime;
+        }
+        if let Some(timezone) = &changes.timezone {
+            node.timezone = timezone.clone();
+        }
+        if let Some(capacity) = changes.capacity {
+            node.capacity = capacity;
+        }
+        node.keywords.retain(|keyword| !changes.removed_keywords.contains(keyword));
+        node.keywords.extend(changes.added_keywords.iter().cloned());
+        data.keywords.extend(changes.added_keywords.iter().cloned());
+
+        // A raised capacity frees places for the waitlist
+        if changes.capacity.is_some() {
+            while promote_from_waitlist(&mut data, id).is_some() {}
+        }
+
+        let after = data.event(id).ok_or(EventRepoError::NotFound(id))?;
+        add_revision(&mut data, action, user, Some(&before), &after);
+        Ok(after)
+    }
+
+    async fn get_featured(
+        &self,
+        registered_since: DateTime<FixedOffset>,
+        limit: u32,
+    ) -> Result<Vec<Event>, EventRepoError> {
+        let data = self.graph.read();
+        let now = Utc::now();
+
+        let mut ranked: Vec<(Event, bool, usize)> = data
+            .active_events()
+            .filter(|(_, node)| node.start_datetime > now)
+            .map(|(id, node)| {
+                let pinned = node.featured_until.is_some_and(|until| until > now);
+                let recent_registrations = data
+                    .registrations
+                    .iter()
+                    .filter(|((_, event_id), edge)| {
+                        event_id == id && edge.state.is_positive() && edge.registered_at >= registered_since
+                    })
+                    .count();
+                (data.to_event(*id, node), pinned, recent_registrations)
+            })
+            .collect();
+
+        ranked.sort_by(|(a, a_pinned, a_recent), (b, b_pinned, b_recent)| {
+            b_pinned
+                .cmp(a_pinned)
+                .then(b_recent.cmp(a_recent))
+                .then(b.attendee_count.cmp(&a.attendee_count))
+                .then(a.start_datetime.cmp(&b.start_datetime))
+                .then(a.id.cmp(&b.id))
+        });
+
+        Ok(ranked
+            .into_iter()
+            .take(limit as usize)
+            .map(|(event, _, _)| event)
+            .collect())
+    }
+
+    async fn set_featured_until(
+        &self,
+        id: EventId,
+        until: Option<DateTime<FixedOffset>>,
+    ) -> Result<Event, EventRepoError> {
+        let mut data = self.graph.write();
+        let node = data
+            .events
+            .get_mut(&id)
+            .filter(|node| node.archived_at.is_none())
+            .ok_or(EventRepoError::NotFound(id))?;
+        node.featured_until = until;
+
+        data.event(id).ok_or(EventRepoError::NotFound(id))
+    }
+
+    async fn get_events_by_keywords(
+        &self,
+        keywords: &KeywordFilter,
+        filter: &EventFilter,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Event>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let mut events: Vec<(Event, usize)> = data
+            .active_events()
+            .filter(|(_, node)| filter.matches(node.start_datetime))
+            .filter_map(|(id, node)| {
+                let matched = keywords.matched(&node.keywords)?;
+                Some((data.to_event(*id, node), matched))
+            })
+            .collect();
+
+        events.sort_by(|(a, a_matched), (b, b_matched)| {
+            b_matched.cmp(a_matched).then_with(|| compare_events(a, b, filter))
+        });
+
+        let total = events.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((
+            events.into_iter().skip(skip).take(limit as usize).map(|(event, _)| event).collect(),
+            total,
+        ))
+    }
+
+    async fn get_events_keywords(&self) -> Result<Vec<String>, EventRepoError> {
+        let data = self.graph.read();
+        Ok(data
+            .keywords
+            .iter()
+            .filter(|keyword| data.active_events().any(|(_, node)| node.keywords.contains(keyword)))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_keyword_stats(
+        &self,
+        sort: KeywordSort,
+        order: SortOrder,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<KeywordStats>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let now = Utc::now();
+
+        let mut stats: Vec<KeywordStats> = data
+            .keywords
+            .iter()
+            .map(|keyword| {
+                let events: Vec<_> = data
+                    .active_events()
+                    .filter(|(_, node)| node.keywords.contains(keyword))
+                    .collect();
+
+                KeywordStats {
+                    name: keyword.clone(),
+                    event_count: events.len() as i64,
+                    upcoming_event_count: events.iter().filter(|(_, node)| node.start_datetime > now).count() as i64,
+                    registration_count: events.iter().map(|(id, node)| data.to_event(**id, node).attendee_count).sum(),
+                }
+            })
+            .filter(|stats| stats.event_count > 0)
+            .collect();
+
+        // Keywords are already sorted by name, which breaks ties
+        stats.sort_by(|a, b| {
+            let ordering = match sort {
+                KeywordSort::Events => a.event_count.cmp(&b.event_count),
+                KeywordSort::Upcoming => a.upcoming_event_count.cmp(&b.upcoming_event_count),
+                KeywordSort::Registrations => a.registration_count.cmp(&b.registration_count),
+                KeywordSort::Name => a.name.cmp(&b.name),
+            };
+            match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+
+        let total = stats.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((stats.into_iter().skip(skip).take(limit as usize).collect(), total))
+    }
+
+    async fn search(
+        &self,
+        terms: &[String],
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<EventSearchHit>, u32), EventRepoError> {
+        let data = self.graph.read();
+        let mut hits: Vec<EventSearchHit> = data
+            .active_events()
+            .filter_map(|(id, node)| {
+                let score = search_score(terms, &node.name)
+                    + node.keywords.iter().map(|kw| search_score(terms, kw)).sum::<f64>();
+                (score > 0.0).then(|| EventSearchHit {
+                    event: data.to_event(*id, node),
+                    score,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.event.id.cmp(&b.event.id)));
+
+        let total = hits.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((hits.into_iter().skip(skip).take(limit as usize).collect(), total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::{MemoryConnection, RegistrationEdge, UserNode};
+    use crate::models::event::{EventListParams, EventPeriod, KeywordMatch};
+    use crate::models::registration::RsvpState;
+
+    fn repository() -> InMemoryEventRepository {
+        let graph = MemoryConnection::new().graph;
+        graph.write().users.insert("org".to_string(), UserNode::default());
+        InMemoryEventRepository::new(graph)
+    }
+
+    fn event(name: &str, start: &str, keywords: &[&str]) -> EventUpdate {
+        EventUpdate {
+            name: name.to_string(),
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            start_datetime: start.to_string(),
+            end_datetime: None,
+            timezone: None,
+            capacity: None,
+        }
+    }
+
+    fn names(events: &[Event]) -> Vec<&str> {
+        events.iter().map(|event| event.name.as_str()).collect()
+    }
+
+    #[rocket::async_test]
+    async fn add_validates_and_allocates_ids() {
+        let repo = repository();
+        let first = repo.add(event("First", "2099-01-01T10:00:00Z", &["music", "music"]), "org").await.unwrap();
+        let second = repo.add(event("Second", "2099-01-02T10:00:00Z", &["sport"]), "org").await.unwrap();
+
+        assert_eq!((first.id, second.id), (1, 2));
+        assert_eq!(first.keywords, ["music"]);
+        assert_eq!(first.organizer.as_deref(), Some("org"));
+        assert_eq!(first.version, 1);
+
+        assert!(repo.add(event("No keywords", "2099-01-01T10:00:00Z", &[]), "org").await.is_err());
+        assert!(repo.add(event("Unknown organizer", "2099-01-01T10:00:00Z", &["music"]), "nobody").await.is_err());
+    }
+
+    #[rocket::async_test]
+    async fn purged_ids_are_not_reused() {
+        let repo = repository();
+        let event = repo.add(event("Old", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.remove(event.id, "org").await.unwrap();
+        let purged = repo.purge_archived(Utc::now().fixed_offset() + chrono::Duration::seconds(1)).await.unwrap();
+        assert_eq!(purged, 1);
+        assert_eq!(repo.find_revisions(event.id, 1, 10).await.unwrap().1, 2);
+
+        let next = repo.add(self::event("New", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        assert_eq!(next.id, 2);
+    }
+
+    #[rocket::async_test]
+    async fn find_all_filters_sorts_and_pages() {
+        let repo = repository();
+        repo.add(event("Past", "2001-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.add(event("Later", "2099-06-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.add(event("Sooner", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        let archived = repo.add(event("Archived", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.remove(archived.id, "org").await.unwrap();
+
+        let upcoming = EventListParams {
+            period: Some(EventPeriod::Upcoming),
+            ..Default::default()
+        }
+        .to_filter()
+        .unwrap();
+        let (events, total) = repo.find_all(&upcoming, 1, 10).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(names(&events), ["Sooner", "Later"]);
+
+        let (events, total) = repo.find_all(&EventFilter::default(), 2, 2).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(names(&events), ["Later"]);
+    }
+
+    #[rocket::async_test]
+    async fn edit_checks_the_version() {
+        let repo = repository();
+        let event = repo.add(event("Name", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        let changes = EventChanges {
+            name: Some("Renamed".to_string()),
+            added_keywords: vec!["live".to_string()],
+            removed_keywords: vec!["music".to_string()],
+            ..Default::default()
+        };
+
+        let edited = repo.edit(event.id, event.version, &changes, RevisionAction::Edited, "org").await.unwrap();
+        assert_eq!(edited.name, "Renamed");
+        assert_eq!(edited.keywords, ["live"]);
+        assert_eq!(edited.version, 2);
+
+        assert!(matches!(
+            repo.edit(event.id, event.version, &changes, RevisionAction::Edited, "org").await,
+            Err(EventRepoError::VersionMismatch(_))
+        ));
+        assert!(matches!(repo.edit(99, 1, &changes, RevisionAction::Edited, "org").await, Err(EventRepoError::NotFound(99))));
+    }
+
+    #[rocket::async_test]
+    async fn raising_the_capacity_promotes_the_waitlist() {
+        let repo = repository();
+        let event = repo
+            .add(EventUpdate { capacity: Some(1), ..event("Name", "2099-01-01T10:00:00Z", &["music"]) }, "org")
+            .await
+            .unwrap();
+        {
+            let mut data = repo.graph.write();
+            data.registrations.insert(("a".to_string(), event.id), RegistrationEdge::new(RsvpState::Going));
+            for (position, name) in [(1, "b"), (2, "c"), (3, "d")] {
+                data.waitlist.insert((name.to_string(), event.id), position);
+            }
+        }
+
+        let changes = EventChanges { capacity: Some(Some(3)), ..Default::default() };
+        let edited = repo.edit(event.id, event.version, &changes, RevisionAction::Edited, "org").await.unwrap();
+        assert_eq!(edited.attendee_count, 3);
+        let data = repo.graph.read();
+        assert_eq!(data.waitlist.keys().collect::<Vec<_>>(), [&("d".to_string(), event.id)]);
+    }
+
+    #[rocket::async_test]
+    async fn remove_and_restore() {
+        let repo = repository();
+        let event = repo.add(event("Name", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+
+        repo.remove(event.id, "org").await.unwrap();
+        assert!(matches!(repo.remove(event.id, "org").await, Err(EventRepoError::NotFound(_))));
+        let (archived, total) = repo.find_archived(Some("org"), 1, 10).await.unwrap();
+        assert_eq!((names(&archived), total), (vec!["Name"], 1));
+        assert_eq!(repo.find_archived(Some("other"), 1, 10).await.unwrap().1, 0);
+
+        let restored = repo.restore(event.id, "org").await.unwrap();
+        assert!(restored.archived_at.is_none());
+        assert!(matches!(repo.restore(event.id, "org").await, Err(EventRepoError::NotArchived(_))));
+    }
+
+    #[rocket::async_test]
+    async fn archived_events_cannot_stay_or_get_featured() {
+        let repo = repository();
+        let event = repo.add(event("Name", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        let until = Some(Utc::now().fixed_offset() + chrono::Duration::days(1));
+
+        let pinned = repo.set_featured_until(event.id, until).await.unwrap();
+        assert_eq!(pinned.featured_until, until);
+        repo.remove(event.id, "org").await.unwrap();
+        assert!(matches!(repo.set_featured_until(event.id, until).await, Err(EventRepoError::NotFound(_))));
+
+        // The pin is gone once the event is back
+        let restored = repo.restore(event.id, "org").await.unwrap();
+        assert!(restored.featured_until.is_none());
+    }
+
+    #[rocket::async_test]
+    async fn revisions_are_numbered_per_event() {
+        let repo = repository();
+        let event = repo.add(event("Name", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        let changes = EventChanges { name: Some("Renamed".to_string()), ..Default::default() };
+        let edited = repo.edit(event.id, event.version, &changes, RevisionAction::Edited, "org").await.unwrap();
+        repo.remove(edited.id, "org").await.unwrap();
+
+        let (revisions, total) = repo.find_revisions(event.id, 1, 2).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(revisions.iter().map(|revision| revision.number).collect::<Vec<_>>(), [3, 2]);
+        assert_eq!(revisions[0].action, RevisionAction::Deleted);
+
+        let since = repo.find_revisions_since(event.id, 2).await.unwrap();
+        assert_eq!(since.iter().map(|revision| revision.number).collect::<Vec<_>>(), [2, 3]);
+    }
+
+    #[rocket::async_test]
+    async fn keyword_filter_modes() {
+        let repo = repository();
+        repo.add(event("Both", "2099-01-01T10:00:00Z", &["music", "live"]), "org").await.unwrap();
+        repo.add(event("Music", "2099-01-02T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.add(event("Online", "2099-01-03T10:00:00Z", &["music", "online"]), "org").await.unwrap();
+
+        let keywords = |mode| KeywordFilter::new(vec!["music".into(), "live".into()], vec!["online".into()], mode);
+        let filter = EventFilter::default();
+
+        let (events, total) = repo.get_events_by_keywords(&keywords(KeywordMatch::All), &filter, 1, 10).await.unwrap();
+        assert_eq!((names(&events), total), (vec!["Both"], 1));
+
+        let (events, total) = repo.get_events_by_keywords(&keywords(KeywordMatch::Any), &filter, 1, 10).await.unwrap();
+        assert_eq!((names(&events), total), (vec!["Both", "Music"], 2));
+    }
+
+    #[rocket::async_test]
+    async fn keyword_stats_count_active_events() {
+        let repo = repository();
+        repo.add(event("Past", "2001-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.add(event("Upcoming", "2099-01-01T10:00:00Z", &["music", "live"]), "org").await.unwrap();
+        let archived = repo.add(event("Archived", "2099-01-01T10:00:00Z", &["music", "chess"]), "org").await.unwrap();
+        repo.remove(archived.id, "org").await.unwrap();
+
+        let (stats, total) = repo.get_keyword_stats(KeywordSort::Events, SortOrder::Desc, 1, 10).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(stats[0].name, "music");
+        assert_eq!((stats[0].event_count, stats[0].upcoming_event_count), (2, 1));
+        assert_eq!(stats[1].name, "live");
+
+        // Keywords left only on archived events offer nothing to filter on
+        assert_eq!(repo.get_events_keywords().await.unwrap(), ["live", "music"]);
+    }
+
+    #[rocket::async_test]
+    async fn search_tolerates_typos() {
+        let repo = repository();
+        repo.add(event("Jazz Festival", "2099-01-01T10:00:00Z", &["music"]), "org").await.unwrap();
+        repo.add(event("Chess Open", "2099-01-02T10:00:00Z", &["games"]), "org").await.unwrap();
+
+        let (hits, total) = repo.search(&search_terms("festivl"), 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(hits[0].event.name, "Jazz Festival");
+
+        let (hits, _) = repo.search(&search_terms("musc"), 1, 10).await.unwrap();
+        assert_eq!(hits[0].event.name, "Jazz Festival");
+        assert_eq!(edit_distance("ab", "ba"), 1);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::db::memory::{MemoryGraph, UserNode};
+use crate::models::user::{Role, User};
+use crate::repo::users::{UserRepoError, UserRepository, new_token_version};
+use crate::utils::pagination::skip;
+use std::sync::Arc;
+
+pub struct InMemoryUserRepository {
+    graph: Arc<MemoryGraph>,
+}
+
+impl InMemoryUserRepository {
+    pub fn new(graph: Arc<MemoryGraph>) -> Self {
+        Self { graph }
+    }
+}
+
+#[rocket::async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn find_one(&self, user_name: &str) -> Result<User, UserRepoError> {
+        let data = self.graph.read();
+        data.users
+            .get(user_name)
+            .map(|node| node.to_user(user_name))
+            .ok_or_else(|| UserRepoError::UserNotFound(user_name.to_string()))
+    }
+
+    async fn find_all(&self, page: u32, limit: u32) -> Result<(Vec<User>, u32), UserRepoError> {
+        let skip = skip(page, limit) as usize;
+
+        let data = self.graph.read();
+        let users = data
+            .users
+            .iter()
+            .skip(skip)
+            .take(limit as usize)
+            .map(|(name, node)| node.to_user(name))
+            .collect();
+
+        Ok((users, data.users.len() as u32))
+    }
+
+    async fn create(&self, user_name: &str, password_hash: &str) -> Result<User, UserRepoError> {
+        let mut data = self.graph.write();
+        if data.users.contains_key(user_name) {
+            return Err(UserRepoError::UserAlreadyExists(user_name.to_string()));
+        }
+
+        let node = UserNode {
+            password_hash: Some(password_hash.to_string()),
+            token_version: new_token_version(),
+            ..Default::default()
+        };
+        let user = node.to_user(user_name);
+        data.users.insert(user_name.to_string(), node);
+
+        Ok(user)
+    }
+
+    async fn update(
+        &self,
+        user_name: &str,
+        new_name: &str,
+        password_hash: Option<&str>,
+    ) -> Result<User, UserRepoError> {
+        let mut data = self.graph.write();
+        if !data.users.contains_key(user_name) {
+            return Err(UserRepoError::UserNotFound(user_name.to_string()));
+        }
+
+        if new_name != user_name && data.users.contains_key(new_name) {
+            return Err(UserRepoError::UserAlreadyExists(new_name.to_string()));
+        }
+
+        let mut node = data.users.remove(user_name).unwrap_or_default();
+        if let Some(password_hash) = password_hash {
+            node.password_hash = Some(password_hash.to_string());
+            node.token_version = new_token_version();
+        }
+        let user = node.to_user(new_name);
+        data.users.insert(new_name.to_string(), node);
+
+        let rename = |name: &String| if name == user_name { new_name.to_string() } else { name.clone() };
+        data.registrations = std::mem::take(&mut data.registrations)
+            .into_iter()
+            .map(|((name, event_id), edge)| ((rename(&name), event_id), edge))
+            .collect();
+        data.waitlist = data
+            .waitlist
+            .iter()
+            .map(|((name, event_id), position)| ((rename(name), *event_id), *position))
+            .collect();
+        data.similar = data
+            .similar
+            .iter()
+            .map(|(from, to)| (rename(from), rename(to)))
+            .collect();
+        data.organizes = data
+            .organizes
+            .iter()
+            .map(|((name, event_id), primary)| ((rename(name), *event_id), *primary))
+            .collect();
+
+        Ok(user)
+    }
+
+    async fn find_password_hash(&self, user_name: &str) -> Result<Option<String>, UserRepoError> {
+        let data = self.graph.read();
+        data.users
+            .get(user_name)
+            .map(|node| node.password_hash.clone())
+            .ok_or_else(|| UserRepoError::UserNotFound(user_name.to_string()))
+    }
+
+    async fn find_token_version(&self, user_name: &str) -> Result<i64, UserRepoError> {
+        let data = self.graph.read();
+        data.users
+            .get(user_name)
+            .map(|node| node.token_version)
+            .ok_or_else(|| UserRepoError::UserNotFound(user_name.to_string()))
+    }
+
+    async fn delete(&self, user_name: &str) -> Result<String, UserRepoError> {
+        let mut data = self.graph.write();
+        if data.users.remove(user_name).is_none() {
+            return Err(UserRepoError::UserNotFound(user_name.to_string()));
+        }
+
+        data.registrations.retain(|(name, _), _| name != user_name);
+        data.waitlist.retain(|(name, _), _| name != user_name);
+        data.similar.retain(|(from, to)| from != user_name && to != user_name);
+        data.organizes.retain(|(name, _), _| name != user_name);
+
+        Ok(format!("User {} successfully deleted", user_name))
+    }
+
+    async fn set_role(&self, user_name: &str, role: Role) -> Result<User, UserRepoError> {
+        let mut data = self.graph.write();
+        let node = data
+            .users
+            .get_mut(user_name)
+            .ok_or_else(|| UserRepoError::UserNotFound(user_name.to_string()))?;
+        node.role = role;
+
+        Ok(node.to_user(user_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::{MemoryConnection, RegistrationEdge};
+    use crate::models::registration::RsvpState;
+
+    fn repository() -> InMemoryUserRepository {
+        InMemoryUserRepository::new(MemoryConnection::new().graph)
+    }
+
+    #[rocket::async_test]
+    async fn create_rejects_taken_names() {
+        let repo = repository();
+        let user = repo.create("alice", "hash").await.unwrap();
+        assert_eq!(user.name, "alice");
+        assert_eq!(user.role, Role::Attendee);
+
+        assert!(matches!(
+            repo.create("alice", "other").await,
+            Err(UserRepoError::UserAlreadyExists(_))
+        ));
+        assert_eq!(repo.find_password_hash("alice").await.unwrap().as_deref(), Some("hash"));
+    }
+
+    #[rocket::async_test]
+    async fn find_all_pages_by_name() {
+        let repo = repository();
+        for name in ["carol", "alice", "bob"] {
+            repo.create(name, "hash").await.unwrap();
+        }
+
+        let (users, total) = repo.find_all(2, 2).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(users.iter().map(|user| user.name.as_str()).collect::<Vec<_>>(), ["carol"]);
+
+        let (users, total) = repo.find_all(5, 2).await.unwrap();
+        assert!(users.is_empty());
+        assert_eq!(total, 3);
+    }
+
+    #[rocket::async_test]
+    async fn update_renames_relationships_and_keeps_the_password() {
+        let graph = MemoryConnection::new().graph;
+        let repo = InMemoryUserRepository::new(graph.clone());
+        repo.create("alice", "hash").await.unwrap();
+        repo.create("bob", "hash").await.unwrap();
+        {
+            let mut data = graph.write();
+            data.registrations.insert(("alice".to_string(), 1), RegistrationEdge::new(RsvpState::Going));
+            data.similar.insert(("bob".to_string(), "alice".to_string()));
+        }
+
+        assert!(matches!(
+            repo.update("alice", "bob", None).await,
+            Err(UserRepoError::UserAlreadyExists(_))
+        ));
+
+        repo.update("alice", "alicia", None).await.unwrap();
+        assert!(matches!(repo.find_one("alice").await, Err(UserRepoError::UserNotFound(_))));
+        assert_eq!(repo.find_password_hash("alicia").await.unwrap().as_deref(), Some("hash"));
+
+        let data = graph.read();
+        assert!(data.registrations.contains_key(&("alicia".to_string(), 1)));
+        assert!(data.similar.contains(&("bob".to_string(), "alicia".to_string())));
+    }
+
+    #[rocket::async_test]
+    async fn password_changes_replace_the_token_version() {
+        let repo = repository();
+        repo.create("alice", "hash").await.unwrap();
+        let version = repo.find_token_version("alice").await.unwrap();
+
+        repo.update("alice", "alicia", None).await.unwrap();
+        assert_eq!(repo.find_token_version("alicia").await.unwrap(), version);
+
+        repo.update("alicia", "alicia", Some("new hash")).await.unwrap();
+        assert_ne!(repo.find_token_version("alicia").await.unwrap(), version);
+    }
+
+    #[rocket::async_test]
+    async fn delete_drops_relationships() {
+        let graph = MemoryConnection::new().graph;
+        let repo = InMemoryUserRepository::new(graph.clone());
+        repo.create("alice", "hash").await.unwrap();
+        graph
+            .write()
+            .registrations
+            .insert(("alice".to_string(), 1), RegistrationEdge::new(RsvpState::Going));
+
+        repo.delete("alice").await.unwrap();
+        assert!(graph.read().registrations.is_empty());
+        assert!(matches!(repo.delete("alice").await, Err(UserRepoError::UserNotFound(_))));
+    }
+
+    #[rocket::async_test]
+    async fn set_role() {
+        let repo = repository();
+        repo.create("alice", "hash").await.unwrap();
+
+        let user = repo.set_role("alice", Role::Organizer).await.unwrap();
+        assert_eq!(user.role, Role::Organizer);
+        assert_eq!(repo.find_one("alice").await.unwrap().role, Role::Organizer);
+    }
+}
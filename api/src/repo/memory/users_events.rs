@@ -0,0 +1,451 @@
+use crate::db::memory::{GraphData, MemoryGraph, RegistrationEdge};
+use crate::models::event::{Event, EventId};
+use crate::models::registration::{Attendee, AttendeeSort, Registration, RsvpState};
+use crate::repo::RepoError;
+use crate::repo::RepoError::Other;
+use crate::repo::users_events::UserEventRepository;
+use crate::utils::pagination::skip;
+use chrono::Utc;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+pub struct InMemoryUserEventRepository {
+    graph: Arc<MemoryGraph>,
+}
+
+impl InMemoryUserEventRepository {
+    pub fn new(graph: Arc<MemoryGraph>) -> InMemoryUserEventRepository {
+        InMemoryUserEventRepository { graph }
+    }
+}
+
+/// IDs of the events the user is registered to with one of `states`.
+fn registered_event_ids(data: &GraphData, user_name: &str, states: &[RsvpState]) -> BTreeSet<EventId> {
+    data.registrations
+        .iter()
+        .filter(|((name, _), edge)| name == user_name && states.contains(&edge.state))
+        .map(|((_, event_id), _)| *event_id)
+        .collect()
+}
+
+fn taken_places(data: &GraphData, event_id: EventId) -> i64 {
+    data.registrations
+        .iter()
+        .filter(|((_, id), edge)| *id == event_id && edge.state.is_positive())
+        .count() as i64
+}
+
+/// Takes the first user off the event's waitlist if a place is free and returns their name.
+pub(crate) fn promote_from_waitlist(data: &mut GraphData, event_id: EventId) -> Option<String> {
+    let capacity = data.events.get(&event_id)?.capacity;
+    if capacity.is_some_and(|capacity| taken_places(data, event_id) >= capacity) {
+        return None;
+    }
+
+    let key = data
+        .waitlist
+        .iter()
+        .filter(|((_, id), _)| *id == event_id)
+        .min_by_key(|(_, position)| **position)
+        .map(|(key, _)| key.clone())?;
+
+    data.waitlist.remove(&key);
+    data.registrations.insert(key.clone(), RegistrationEdge::new(RsvpState::Going));
+    Some(key.0)
+}
+
+#[rocket::async_trait]
+impl UserEventRepository for InMemoryUserEventRepository {
+    async fn assign_user_to_event(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+        state: RsvpState,
+        join_waitlist: bool,
+    ) -> Result<Registration, RepoError> {
+        let mut data = self.graph.write();
+        let Some(capacity) = data
+            .events
+            .get(&event_id)
+            .filter(|event| event.archived_at.is_none())
+            .map(|event| event.capacity)
+        else {
+            return Err(Other("No result returned from query".to_string()));
+        };
+        if !data.users.contains_key(user_name) {
+            return Err(Other("No result returned from query".to_string()));
+        }
+
+        let key = (user_name.to_string(), event_id);
+        let holds_place = data.registrations.get(&key).is_some_and(|edge| edge.state.is_positive());
+
+        if !state.is_positive()
+            || holds_place
+            || capacity.is_none_or(|capacity| taken_places(&data, event_id) < capacity)
+        {
+            data.waitlist.remove(&key);
+            data.registrations
+                .entry(key)
+                .and_modify(|edge| edge.state = state)
+                .or_insert_with(|| RegistrationEdge::new(state));
+            // Switching to a state without a place frees one up
+            promote_from_waitlist(&mut data, event_id);
+            return Ok(Registration::Registered);
+        }
+
+        if !data.waitlist.contains_key(&key) {
+            if !join_waitlist {
+                return Ok(Registration::Full);
+            }
+
+            let Some(event) = data.events.get_mut(&event_id) else {
+                return Err(Other("No result returned from query".to_string()));
+            };
+            event.waitlist_counter += 1;
+            let position = event.waitlist_counter;
+            data.waitlist.insert(key.clone(), position);
+        }
+
+        let own = data.waitlist[&key];
+        let position = data
+            .waitlist
+            .iter()
+            .filter(|((_, id), position)| *id == event_id && **position <= own)
+            .count() as i64;
+        Ok(Registration::Waitlisted { position })
+    }
+
+    async fn unassign_user_from_event(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+    ) -> Result<Option<String>, RepoError> {
+        let mut data = self.graph.write();
+
+        let key = (user_name.to_string(), event_id);
+        data.registrations.remove(&key);
+        data.waitlist.remove(&key);
+
+        Ok(promote_from_waitlist(&mut data, event_id))
+    }
+
+    async fn find_attendees(
+        &self,
+        event_id: EventId,
+        states: &[RsvpState],
+        sort: AttendeeSort,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Attendee>, u32), RepoError> {
+        let data = self.graph.read();
+        let mut attendees: Vec<Attendee> = data
+            .registrations
+            .iter()
+            .filter(|((_, id), edge)| *id == event_id && states.contains(&edge.state))
+            .map(|((name, _), edge)| Attendee {
+                name: name.clone(),
+                state: edge.state,
+                registered_at: Some(edge.registered_at),
+            })
+            .collect();
+
+        // Registrations are keyed by name, so they are already sorted by it
+        if sort == AttendeeSort::RegisteredAt {
+            attendees.sort_by_key(|attendee| attendee.registered_at);
+        }
+
+        let total = attendees.len() as u32;
+        let skip = skip(page, limit) as usize;
+        Ok((attendees.into_iter().skip(skip).take(limit as usize).collect(), total))
+    }
+
+    async fn find_all_events_of_user(
+        &self,
+        user_name: &str,
+        states: &[RsvpState],
+    ) -> Result<Vec<Event>, RepoError> {
+        let data = self.graph.read();
+        Ok(registered_event_ids(&data, user_name, states)
+            .into_iter()
+            .filter_map(|id| data.event(id))
+            .filter(|event| event.archived_at.is_none())
+            .collect())
+    }
+
+    async fn recommend_events_for_user_based_on_events_similarity(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<Event>, RepoError> {
+        let data = self.graph.read();
+        let now = Utc::now();
+        let registered = registered_event_ids(&data, user_name, RsvpState::ALL);
+        let liked = registered_event_ids(&data, user_name, RsvpState::POSITIVE);
+
+        // One score per (liked event, candidate) pair, deduplicated on
+        // (candidate, score) like the `WITH DISTINCT` in the Cypher query.
+        let mut scored = Vec::<(EventId, f64)>::new();
+        for event_id in &liked {
+            let Some(event) = data.events.get(event_id) else { continue };
+
+            for (other_id, other) in data.active_events() {
+                if other.start_datetime <= now || registered.contains(other_id) {
+                    continue;
+                }
+
+                let intersection = other
+                    .keywords
+                    .iter()
+                    .filter(|kw| event.keywords.contains(kw))
+                    .count();
+                if intersection == 0 {
+                    continue;
+                }
+
+                let union = event.keywords.len()
+                    + other
+                        .keywords
+                        .iter()
+                        .filter(|kw| !event.keywords.contains(kw))
+                        .count();
+                let jaccard = intersection as f64 / union as f64;
+
+                if jaccard > 0.5 && !scored.contains(&(*other_id, jaccard)) {
+                    scored.push((*other_id, jaccard));
+                }
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        Ok(scored
+            .into_iter()
+            .filter_map(|(id, _)| data.event(id))
+            .collect())
+    }
+
+    async fn recommend_events_for_user_based_on_users_similarity(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<Event>, RepoError> {
+        let data = self.graph.read();
+        let now = Utc::now();
+        let registered = registered_event_ids(&data, user_name, RsvpState::ALL);
+        if registered_event_ids(&data, user_name, RsvpState::POSITIVE).is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let recommended: BTreeSet<EventId> = data
+            .similar
+            .iter()
+            .filter(|(name, _)| name == user_name)
+            .flat_map(|(_, similar_user)| registered_event_ids(&data, similar_user, RsvpState::POSITIVE))
+            .filter(|id| !registered.contains(id))
+            .filter(|id| {
+                data.events
+                    .get(id)
+                    .is_some_and(|e| e.start_datetime > now && e.archived_at.is_none())
+            })
+            .collect();
+
+        Ok(recommended
+            .into_iter()
+            .filter_map(|id| data.event(id))
+            .collect())
+    }
+
+    async fn find_registration_state(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+    ) -> Result<Option<RsvpState>, RepoError> {
+        let data = self.graph.read();
+        if !data.users.contains_key(user_name) || !data.events.contains_key(&event_id) {
+            return Err(Other("No result returned from query".to_string()));
+        }
+
+        Ok(data
+            .registrations
+            .get(&(user_name.to_string(), event_id))
+            .map(|edge| edge.state))
+    }
+
+    async fn find_events_organized_by_user(&self, user_name: &str) -> Result<Vec<Event>, RepoError> {
+        let data = self.graph.read();
+        Ok(data
+            .organizes
+            .keys()
+            .filter(|(name, _)| name == user_name)
+            .filter_map(|(_, event_id)| data.event(*event_id))
+            .filter(|event| event.archived_at.is_none())
+            .collect())
+    }
+
+    async fn add_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError> {
+        let mut data = self.graph.write();
+        if data.users.contains_key(user_name) && data.events.contains_key(&event_id) {
+            data.organizes.entry((user_name.to_string(), event_id)).or_insert(false);
+        }
+        Ok(())
+    }
+
+    async fn remove_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError> {
+        let mut data = self.graph.write();
+        let key = (user_name.to_string(), event_id);
+        if data.organizes.get(&key) == Some(&false) {
+            data.organizes.remove(&key);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::{EventNode, MemoryConnection, UserNode};
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    fn node(start: DateTime<FixedOffset>, capacity: Option<i64>, keywords: &[&str]) -> EventNode {
+        EventNode {
+            name: "Event".to_string(),
+            start_datetime: start,
+            end_datetime: None,
+            timezone: None,
+            capacity,
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            featured_until: None,
+            archived_at: None,
+            version: 1,
+            waitlist_counter: 0,
+        }
+    }
+
+    fn upcoming() -> DateTime<FixedOffset> {
+        (Utc::now() + Duration::days(30)).fixed_offset()
+    }
+
+    /// A repository over users `a` to `d` and `events`, keyed from 1.
+    fn repository(events: Vec<EventNode>) -> InMemoryUserEventRepository {
+        let graph = MemoryConnection::new().graph;
+        {
+            let mut data = graph.write();
+            for name in ["a", "b", "c", "d"] {
+                data.users.insert(name.to_string(), UserNode::default());
+            }
+            for (id, event) in (1..).zip(events) {
+                data.events.insert(id, event);
+            }
+        }
+        InMemoryUserEventRepository::new(graph)
+    }
+
+    #[rocket::async_test]
+    async fn full_events_waitlist_and_promote() {
+        let repo = repository(vec![node(upcoming(), Some(1), &["music"])]);
+
+        assert_eq!(repo.assign_user_to_event("a", 1, RsvpState::Going, true).await.unwrap(), Registration::Registered);
+        assert_eq!(repo.assign_user_to_event("b", 1, RsvpState::Going, false).await.unwrap(), Registration::Full);
+        assert_eq!(
+            repo.assign_user_to_event("b", 1, RsvpState::Going, true).await.unwrap(),
+            Registration::Waitlisted { position: 1 }
+        );
+        assert_eq!(
+            repo.assign_user_to_event("c", 1, RsvpState::Going, true).await.unwrap(),
+            Registration::Waitlisted { position: 2 }
+        );
+        // States without a place are always accepted
+        assert_eq!(repo.assign_user_to_event("d", 1, RsvpState::Maybe, false).await.unwrap(), Registration::Registered);
+
+        assert_eq!(repo.unassign_user_from_event("a", 1).await.unwrap().as_deref(), Some("b"));
+        assert_eq!(repo.find_registration_state("b", 1).await.unwrap(), Some(RsvpState::Going));
+
+        // Declining frees the place for the next in line
+        repo.assign_user_to_event("b", 1, RsvpState::Declined, false).await.unwrap();
+        assert_eq!(repo.find_registration_state("c", 1).await.unwrap(), Some(RsvpState::Going));
+    }
+
+    #[rocket::async_test]
+    async fn archived_events_refuse_registrations() {
+        let mut event = node(upcoming(), None, &["music"]);
+        event.archived_at = Some(Utc::now().fixed_offset());
+        let repo = repository(vec![event]);
+
+        assert!(repo.assign_user_to_event("a", 1, RsvpState::Going, true).await.is_err());
+    }
+
+    #[rocket::async_test]
+    async fn find_attendees_filters_states_and_pages() {
+        let repo = repository(vec![node(upcoming(), None, &["music"])]);
+        repo.assign_user_to_event("c", 1, RsvpState::Going, false).await.unwrap();
+        repo.assign_user_to_event("a", 1, RsvpState::Maybe, false).await.unwrap();
+        repo.assign_user_to_event("b", 1, RsvpState::Declined, false).await.unwrap();
+
+        let (attendees, total) = repo
+            .find_attendees(1, RsvpState::INTERESTED, AttendeeSort::Name, 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(attendees.iter().map(|attendee| attendee.name.as_str()).collect::<Vec<_>>(), ["a", "c"]);
+
+        let (attendees, total) = repo
+            .find_attendees(1, RsvpState::ALL, AttendeeSort::RegisteredAt, 2, 2)
+            .await
+            .unwrap();
+        assert_eq!((attendees.len(), total), (1, 3));
+    }
+
+    #[rocket::async_test]
+    async fn events_of_user_by_state() {
+        let repo = repository(vec![node(upcoming(), None, &["music"]), node(upcoming(), None, &["sport"])]);
+        repo.assign_user_to_event("a", 1, RsvpState::Going, false).await.unwrap();
+        repo.assign_user_to_event("a", 2, RsvpState::Declined, false).await.unwrap();
+
+        let events = repo.find_all_events_of_user("a", RsvpState::INTERESTED).await.unwrap();
+        assert_eq!(events.iter().map(|event| event.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(repo.find_all_events_of_user("a", RsvpState::ALL).await.unwrap().len(), 2);
+    }
+
+    #[rocket::async_test]
+    async fn events_of_user_leave_out_archived_events() {
+        let repo = repository(vec![node(upcoming(), None, &["music"]), node(upcoming(), None, &["sport"])]);
+        repo.assign_user_to_event("a", 1, RsvpState::Going, false).await.unwrap();
+        repo.assign_user_to_event("a", 2, RsvpState::Going, false).await.unwrap();
+        repo.graph.write().events.get_mut(&2).unwrap().archived_at = Some(Utc::now().fixed_offset());
+
+        let events = repo.find_all_events_of_user("a", RsvpState::ALL).await.unwrap();
+        assert_eq!(events.iter().map(|event| event.id).collect::<Vec<_>>(), [1]);
+    }
+
+    #[rocket::async_test]
+    async fn recommendations() {
+        let past = (Utc::now() - Duration::days(30)).fixed_offset();
+        let repo = repository(vec![
+            node(past, None, &["music", "live"]),
+            node(upcoming(), None, &["music", "live"]),
+            node(upcoming(), None, &["sport"]),
+        ]);
+        repo.assign_user_to_event("a", 1, RsvpState::Attended, false).await.unwrap();
+        repo.assign_user_to_event("b", 3, RsvpState::Going, false).await.unwrap();
+        repo.graph.write().similar.insert(("a".to_string(), "b".to_string()));
+
+        let by_events = repo.recommend_events_for_user_based_on_events_similarity("a").await.unwrap();
+        assert_eq!(by_events.iter().map(|event| event.id).collect::<Vec<_>>(), [2]);
+
+        let by_users = repo.recommend_events_for_user_based_on_users_similarity("a").await.unwrap();
+        assert_eq!(by_users.iter().map(|event| event.id).collect::<Vec<_>>(), [3]);
+    }
+
+    #[rocket::async_test]
+    async fn co_organizers() {
+        let repo = repository(vec![node(upcoming(), None, &["music"])]);
+        repo.graph.write().organizes.insert(("a".to_string(), 1), true);
+
+        repo.add_co_organizer("b", 1).await.unwrap();
+        assert_eq!(repo.find_events_organized_by_user("b").await.unwrap().len(), 1);
+
+        // The primary organizer cannot be removed as a co-organizer
+        repo.remove_co_organizer("a", 1).await.unwrap();
+        repo.remove_co_organizer("b", 1).await.unwrap();
+        assert_eq!(repo.find_events_organized_by_user("a").await.unwrap().len(), 1);
+        assert!(repo.find_events_organized_by_user("b").await.unwrap().is_empty());
+    }
+}
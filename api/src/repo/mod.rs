@@ -23,6 +23,8 @@ impl ApiError for RepoError {
     }
 }
 
+pub mod dataset;
 pub mod events;
+pub mod memory;
 pub mod users;
 pub mod users_events;
\ No newline at end of file
@@ -1,6 +1,8 @@
-use crate::models::user::User;
+use crate::models::user::{Role, User};
 use crate::repo::RepoError::Other;
+use crate::repo::events::count;
 use crate::repo::{ApiError, RepoError};
+use crate::utils::pagination::skip;
 use neo4rs::{Graph, query};
 use rocket::http::Status;
 use std::sync::Arc;
@@ -12,6 +14,8 @@ pub enum UserRepoError {
     RepoError(#[from] RepoError),
     #[error("User not found with user name: {0}")]
     UserNotFound(String),
+    #[error("User already exists with user name: {0}")]
+    UserAlreadyExists(String),
 }
 
 impl ApiError for UserRepoError {
@@ -19,25 +23,73 @@ impl ApiError for UserRepoError {
         match self {
             UserRepoError::RepoError(e) => e.status(),
             UserRepoError::UserNotFound(_) => Status::BadRequest,
+            UserRepoError::UserAlreadyExists(_) => Status::Conflict,
         }
     }
 }
 
-pub struct UserRepository {
+#[rocket::async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_one(&self, user_name: &str) -> Result<User, UserRepoError>;
+
+    async fn find_all(&self, page: u32, limit: u32) -> Result<(Vec<User>, u32), UserRepoError>;
+
+    async fn create(&self, user_name: &str, password_hash: &str) -> Result<User, UserRepoError>;
+
+    /// Renames the user and, when `password_hash` is given, replaces their password.
+    async fn update(
+        &self,
+        user_name: &str,
+        new_name: &str,
+        password_hash: Option<&str>,
+    ) -> Result<User, UserRepoError>;
+
+    /// Returns the stored password hash, `None` for users imported without one.
+    async fn find_password_hash(&self, user_name: &str) -> Result<Option<String>, UserRepoError>;
+
+    /// Returns the version the user's tokens must carry. It changes with the password,
+    /// so tokens issued before a password change stop working.
+    async fn find_token_version(&self, user_name: &str) -> Result<i64, UserRepoError>;
+
+    async fn delete(&self, user_name: &str) -> Result<String, UserRepoError>;
+
+    async fn set_role(&self, user_name: &str, role: Role) -> Result<User, UserRepoError>;
+}
+
+/// Picks the token version of a new password. It is random rather than a counter so a user
+/// deleted and registered again under the same name does not accept the old user's tokens.
+pub fn new_token_version() -> i64 {
+    rand::random()
+}
+
+/// Maps a violation of the `user_name` uniqueness constraint to a conflict,
+/// which can still happen when two writes race past the existence checks.
+fn map_write_error(e: neo4rs::Error, user_name: &str) -> UserRepoError {
+    if e.to_string().contains("ConstraintValidationFailed") {
+        UserRepoError::UserAlreadyExists(user_name.to_string())
+    } else {
+        UserRepoError::RepoError(Other(e.to_string()))
+    }
+}
+
+pub struct Neo4jUserRepository {
     graph: Arc<Graph>,
 }
 
-impl UserRepository {
+impl Neo4jUserRepository {
     pub fn new(graph: Arc<Graph>) -> Self {
         Self { graph }
     }
+}
 
-    pub async fn find_one(&self, user_name: &str) -> Result<User, UserRepoError> {
+#[rocket::async_trait]
+impl UserRepository for Neo4jUserRepository {
+    async fn find_one(&self, user_name: &str) -> Result<User, UserRepoError> {
         let result = self
             .graph
             .execute(
                 query("MATCH (u:User) WHERE u.name = $name RETURN u")
-                    .param("name", user_name.clone()),
+                    .param("name", user_name),
             )
             .await;
 
@@ -52,8 +104,12 @@ impl UserRepository {
         }
     }
 
-    pub async fn find_all(&self, page: u32, limit: u32) -> Result<(Vec<User>, u32), UserRepoError> {
-        let skip = (page - 1) * limit;
+    async fn find_all(&self, page: u32, limit: u32) -> Result<(Vec<User>, u32), UserRepoError> {
+        let skip = skip(page, limit);
+
+        let total = count(&self.graph, query("MATCH (u:User) RETURN count(u) AS total"))
+            .await
+            .map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
 
         // let result = self.graph.execute(query("MATCH (u:User) RETURN u")).await;
         let result = self
@@ -61,13 +117,11 @@ impl UserRepository {
             .execute(
                 query(
                     "MATCH (u:User)
-                      WITH count(u) AS total
-                      MATCH (u:User)
-                      RETURN u, total
+                      RETURN u
                       ORDER BY u.name
                       SKIP $skip LIMIT $limit",
                 )
-                .param("skip", skip as i64)
+                .param("skip", skip)
                 .param("limit", limit as i64),
             )
             .await
@@ -76,14 +130,147 @@ impl UserRepository {
         let mut users = Vec::<User>::new();
 
         let mut rows = result;
-        let mut total = 0;
 
         while let Some(row) = rows.next().await.map_err(|e| UserRepoError::RepoError(Other(e.to_string())))? {
             let user: User = row.get("u").map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
-            total = row.get::<i64>("total").unwrap_or(0) as u32;
             users.push(user);
         }
 
         Ok((users, total))
     }
+
+    async fn create(&self, user_name: &str, password_hash: &str) -> Result<User, UserRepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                query(
+                    "MERGE (u:User {name: $name})
+                      ON CREATE SET u.justCreated = true,
+                                    u.passwordHash = $passwordHash,
+                                    u.tokenVersion = $tokenVersion
+                      WITH u, u.justCreated IS NOT NULL AS created
+                      REMOVE u.justCreated
+                      RETURN u, created",
+                )
+                .param("name", user_name)
+                .param("passwordHash", password_hash)
+                .param("tokenVersion", new_token_version()),
+            )
+            .await
+            .map_err(|e| map_write_error(e, user_name))?;
+
+        let row = rows
+            .next()
+            .await
+            .map_err(|e| map_write_error(e, user_name))?
+            .ok_or_else(|| UserRepoError::RepoError(Other("Can't create user".to_string())))?;
+
+        let created: bool = row.get("created").map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
+        if !created {
+            return Err(UserRepoError::UserAlreadyExists(user_name.to_string()));
+        }
+
+        row.get("u").map_err(|e| UserRepoError::RepoError(Other(e.to_string())))
+    }
+
+    async fn update(
+        &self,
+        user_name: &str,
+        new_name: &str,
+        password_hash: Option<&str>,
+    ) -> Result<User, UserRepoError> {
+        self.find_one(user_name).await?;
+
+        let mut rows = self
+            .graph
+            .execute(
+                query(
+                    "MATCH (u:User {name: $name})
+                      OPTIONAL MATCH (taken:User {name: $newName})
+                      WITH u, taken
+                      WHERE taken IS NULL OR taken = u
+                      SET u.name = $newName,
+                          u.passwordHash = coalesce($passwordHash, u.passwordHash),
+                          u.tokenVersion = CASE WHEN $passwordHash IS NULL THEN u.tokenVersion ELSE $tokenVersion END
+                      RETURN u",
+                )
+                .param("name", user_name)
+                .param("newName", new_name)
+                .param("passwordHash", password_hash)
+                .param("tokenVersion", new_token_version()),
+            )
+            .await
+            .map_err(|e| map_write_error(e, new_name))?;
+
+        match rows.next().await.map_err(|e| map_write_error(e, new_name))? {
+            Some(row) => row.get("u").map_err(|e| UserRepoError::RepoError(Other(e.to_string()))),
+            None => Err(UserRepoError::UserAlreadyExists(new_name.to_string())),
+        }
+    }
+
+    async fn find_password_hash(&self, user_name: &str) -> Result<Option<String>, UserRepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                query("MATCH (u:User {name: $name}) RETURN u.passwordHash AS passwordHash")
+                    .param("name", user_name),
+            )
+            .await
+            .map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
+
+        match rows.next().await.map_err(|e| UserRepoError::RepoError(Other(e.to_string())))? {
+            Some(row) => row
+                .get::<Option<String>>("passwordHash")
+                .map_err(|e| UserRepoError::RepoError(Other(e.to_string()))),
+            None => Err(UserRepoError::UserNotFound(user_name.to_string())),
+        }
+    }
+
+    async fn find_token_version(&self, user_name: &str) -> Result<i64, UserRepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                // Users created before token versions existed have none yet
+                query("MATCH (u:User {name: $name}) RETURN coalesce(u.tokenVersion, 0) AS tokenVersion")
+                    .param("name", user_name),
+            )
+            .await
+            .map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
+
+        match rows.next().await.map_err(|e| UserRepoError::RepoError(Other(e.to_string())))? {
+            Some(row) => row
+                .get::<i64>("tokenVersion")
+                .map_err(|e| UserRepoError::RepoError(Other(e.to_string()))),
+            None => Err(UserRepoError::UserNotFound(user_name.to_string())),
+        }
+    }
+
+    async fn delete(&self, user_name: &str) -> Result<String, UserRepoError> {
+        self.find_one(user_name).await?;
+
+        // DETACH DELETE also drops the user's REGISTERED_TO and SIMILAR relationships
+        self.graph
+            .run(query("MATCH (u:User {name: $name}) DETACH DELETE u").param("name", user_name))
+            .await
+            .map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
+
+        Ok(format!("User {} successfully deleted", user_name))
+    }
+
+    async fn set_role(&self, user_name: &str, role: Role) -> Result<User, UserRepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                query("MATCH (u:User {name: $name}) SET u.role = $role RETURN u")
+                    .param("name", user_name)
+                    .param("role", role.as_str()),
+            )
+            .await
+            .map_err(|e| UserRepoError::RepoError(Other(e.to_string())))?;
+
+        match rows.next().await.map_err(|e| UserRepoError::RepoError(Other(e.to_string())))? {
+            Some(row) => row.get("u").map_err(|e| UserRepoError::RepoError(Other(e.to_string()))),
+            None => Err(UserRepoError::UserNotFound(user_name.to_string())),
+        }
+    }
 }
@@ -1,80 +1,328 @@
-use crate::models::event::Event;
+use crate::models::event::{Event, EventId};
+use crate::models::registration::{Attendee, AttendeeSort, Registration, RsvpState};
 use crate::repo::RepoError;
+use crate::repo::events::{count, event_query};
 use crate::repo::RepoError::Other;
+use crate::utils::pagination::skip;
 use neo4rs::{Graph, query};
 use std::sync::Arc;
 
-pub struct UserEventRepository {
+#[rocket::async_trait]
+pub trait UserEventRepository: Send + Sync {
+    /// Registers the user with the given RSVP state, or puts them on the waitlist when
+    /// the state takes a place, the event is full and `join_waitlist` is set. Users
+    /// already holding a place or waitlisted keep it.
+    async fn assign_user_to_event(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+        state: RsvpState,
+        join_waitlist: bool,
+    ) -> Result<Registration, RepoError>;
+
+    /// Drops the user's registration or waitlist entry and returns the name of the
+    /// waitlisted user promoted into the freed place, if any.
+    async fn unassign_user_from_event(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+    ) -> Result<Option<String>, RepoError>;
+
+    /// One page of the users registered to the event with one of `states`, and their total.
+    async fn find_attendees(
+        &self,
+        event_id: EventId,
+        states: &[RsvpState],
+        sort: AttendeeSort,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Attendee>, u32), RepoError>;
+
+    /// Events the user is registered to with one of `states`.
+    async fn find_all_events_of_user(
+        &self,
+        user_name: &str,
+        states: &[RsvpState],
+    ) -> Result<Vec<Event>, RepoError>;
+
+    async fn recommend_events_for_user_based_on_events_similarity(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<Event>, RepoError>;
+
+    async fn recommend_events_for_user_based_on_users_similarity(
+        &self,
+        user_name: &str,
+    ) -> Result<Vec<Event>, RepoError>;
+
+    /// The user's RSVP state for the event, `None` when they are not registered.
+    async fn find_registration_state(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+    ) -> Result<Option<RsvpState>, RepoError>;
+
+    async fn find_events_organized_by_user(&self, user_name: &str) -> Result<Vec<Event>, RepoError>;
+
+    async fn add_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError>;
+
+    async fn remove_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError>;
+}
+
+/// Takes the first user off the waitlist of `e` if a place is free, exposing their
+/// name as `promoted`. Expects a single row with the event locked.
+const PROMOTE_FROM_WAITLIST: &str = "
+    OPTIONAL MATCH (next:User)-[w:WAITLISTED]->(e)
+    WITH *
+    ORDER BY w.position
+    LIMIT 1
+    WITH *,
+         next IS NOT NULL
+           AND (e.capacity IS NULL
+                OR COUNT { (:User)-[p:REGISTERED_TO]->(e) WHERE coalesce(p.state, 'going') IN $positiveStates } < e.capacity)
+         AS promote
+    FOREACH (_ IN CASE WHEN promote THEN [1] ELSE [] END |
+      DELETE w
+      MERGE (next)-[promotedRel:REGISTERED_TO]->(e)
+        ON CREATE SET promotedRel.registeredAt = datetime()
+      SET promotedRel.state = 'going')
+    WITH *, CASE WHEN promote THEN next.name END AS promoted";
+
+/// Registers as many users from the waitlist of `e` as there are free places, in waitlist
+/// order. Used when an event's capacity is raised or removed.
+pub(crate) const PROMOTE_ALL_FROM_WAITLIST: &str = "
+    WITH e
+    OPTIONAL MATCH (waiting:User)-[w:WAITLISTED]->(e)
+    WITH e, waiting, w
+    ORDER BY w.position
+    WITH e, collect(waiting) AS waiting, collect(w) AS queue
+    WITH e, waiting, queue,
+         CASE WHEN e.capacity IS NULL THEN size(queue)
+              ELSE e.capacity - COUNT { (:User)-[p:REGISTERED_TO]->(e) WHERE coalesce(p.state, 'going') IN $positiveStates }
+         END AS free
+    WITH e, waiting, queue, CASE WHEN free > 0 THEN free ELSE 0 END AS free
+    FOREACH (w IN queue[0..free] | DELETE w)
+    FOREACH (next IN waiting[0..free] |
+      MERGE (next)-[promotedRel:REGISTERED_TO]->(e)
+        ON CREATE SET promotedRel.registeredAt = datetime()
+      SET promotedRel.state = 'going')";
+
+/// The events `$n` is registered to in one of `$states`, archived ones left out.
+const EVENTS_OF_USER: &str = "
+    MATCH (u:User {name: $n})
+    MATCH (u)-[r:REGISTERED_TO]->(e:Event)
+    WHERE coalesce(r.state, 'going') IN $states AND e.archivedAt IS NULL
+    OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+    RETURN
+       {event_columns},
+       collect(k.name)    AS keywords";
+
+pub struct Neo4jUserEventRepository {
     graph: Arc<Graph>,
 }
 
-impl UserEventRepository {
-    pub fn new(graph: Arc<Graph>) -> UserEventRepository {
-        UserEventRepository { graph }
+impl Neo4jUserEventRepository {
+    pub fn new(graph: Arc<Graph>) -> Neo4jUserEventRepository {
+        Neo4jUserEventRepository { graph }
     }
+}
 
-    pub async fn assign_user_to_event(
+#[rocket::async_trait]
+impl UserEventRepository for Neo4jUserEventRepository {
+    async fn assign_user_to_event(
         &self,
         user_name: &str,
-        event_id: u16,
-    ) -> Result<(), RepoError> {
-        self.graph
-            .run(
-                query(
-                    "\
-                MATCH (u:User {name: $n})\
-                MATCH (e:Event {id: $id})\
-                MERGE (u)-[:REGISTERED_TO]->(e)\
-                ",
-                )
-                .param("n", user_name)
-                .param("id", event_id),
+        event_id: EventId,
+        state: RsvpState,
+        join_waitlist: bool,
+    ) -> Result<Registration, RepoError> {
+        let cypher = "\
+                MATCH (u:User {name: $n})
+                MATCH (e:Event {id: $id})
+                WHERE e.archivedAt IS NULL
+
+                // Setting the lock property first makes concurrent registrations for the
+                // same event wait for each other, so the capacity check cannot be raced
+                SET e._lock = true
+                WITH u, e,
+                     EXISTS {
+                       (u)-[r:REGISTERED_TO]->(e) WHERE coalesce(r.state, 'going') IN $positiveStates
+                     } AS holdsPlace,
+                     COUNT {
+                       (:User)-[r:REGISTERED_TO]->(e) WHERE coalesce(r.state, 'going') IN $positiveStates
+                     } AS taken
+                OPTIONAL MATCH (u)-[queued:WAITLISTED]->(e)
+                WITH u, e, queued,
+                     CASE
+                       WHEN NOT $state IN $positiveStates OR holdsPlace
+                            OR e.capacity IS NULL OR taken < e.capacity THEN 'registered'
+                       WHEN queued IS NOT NULL OR $joinWaitlist THEN 'waitlisted'
+                       ELSE 'full'
+                     END AS outcome
+
+                // Register, leaving the waitlist if there was a place for the user after all
+                FOREACH (_ IN CASE WHEN outcome = 'registered' THEN [1] ELSE [] END |
+                  MERGE (u)-[r:REGISTERED_TO]->(e)
+                    ON CREATE SET r.registeredAt = datetime()
+                  SET r.state = $state)
+                FOREACH (_ IN CASE WHEN outcome = 'registered' AND queued IS NOT NULL THEN [1] ELSE [] END |
+                  DELETE queued)
+
+                // Join the end of the queue, positions come from a per-event counter
+                FOREACH (_ IN CASE WHEN outcome = 'waitlisted' AND queued IS NULL THEN [1] ELSE [] END |
+                  SET e.waitlistCounter = coalesce(e.waitlistCounter, 0) + 1
+                  CREATE (u)-[:WAITLISTED {position: e.waitlistCounter}]->(e))
+
+                // Switching to a state without a place frees one up
+                WITH u, e, outcome
+                {promote_from_waitlist}
+                REMOVE e._lock
+
+                WITH u, e, outcome
+                OPTIONAL MATCH (u)-[own:WAITLISTED]->(e)
+                RETURN outcome,
+                       COUNT { (:User)-[w:WAITLISTED]->(e) WHERE w.position <= own.position } AS position
+                ";
+
+        let mut rows = self
+            .graph
+            .execute(
+                query(&cypher.replace("{promote_from_waitlist}", PROMOTE_FROM_WAITLIST))
+                    .param("n", user_name)
+                    .param("id", event_id)
+                    .param("state", state.as_str())
+                    .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
+                    .param("joinWaitlist", join_waitlist),
             )
             .await
             .map_err(|e| Other(e.to_string()))?;
-        Ok(())
+
+        let row = match rows.next().await {
+            Ok(Some(row)) => row,
+            Ok(None) => return Err(Other("No result returned from query".to_string())),
+            Err(e) => return Err(Other(e.to_string())),
+        };
+
+        let outcome: String = row.get("outcome").map_err(|e| Other(e.to_string()))?;
+        match outcome.as_str() {
+            "registered" => Ok(Registration::Registered),
+            "waitlisted" => Ok(Registration::Waitlisted {
+                position: row.get("position").map_err(|e| Other(e.to_string()))?,
+            }),
+            _ => Ok(Registration::Full),
+        }
     }
 
-    pub async fn unassign_user_from_event(
+    async fn unassign_user_from_event(
         &self,
         user_name: &str,
-        event_id: u16,
-    ) -> Result<(), RepoError> {
-        self.graph
-            .run(
-                query(
-                    "\
-                MATCH (u:User {name: $n})\
-                MATCH (e:Event {id: $id})\
-                MATCH (u)-[r:REGISTERED_TO]->(e)\
-                DELETE r\
-                ",
-                )
-                .param("n", user_name)
-                .param("id", event_id),
+        event_id: EventId,
+    ) -> Result<Option<String>, RepoError> {
+        let cypher = "\
+                MATCH (e:Event {id: $id})
+                SET e._lock = true
+                WITH e
+                OPTIONAL MATCH (:User {name: $n})-[r:REGISTERED_TO|WAITLISTED]->(e)
+                DELETE r
+
+                // Promote the head of the waitlist if a place is free now
+                WITH DISTINCT e
+                {promote_from_waitlist}
+                REMOVE e._lock
+                RETURN promoted
+                ";
+
+        let mut rows = self
+            .graph
+            .execute(
+                query(&cypher.replace("{promote_from_waitlist}", PROMOTE_FROM_WAITLIST))
+                    .param("n", user_name)
+                    .param("id", event_id)
+                    .param("positiveStates", RsvpState::names(RsvpState::POSITIVE)),
             )
             .await
             .map_err(|e| Other(e.to_string()))?;
-        Ok(())
+
+        match rows.next().await {
+            Ok(Some(row)) => row.get("promoted").map_err(|e| Other(e.to_string())),
+            Ok(None) => Ok(None),
+            Err(e) => Err(Other(e.to_string())),
+        }
     }
 
-    pub async fn find_all_events_of_user(&self, user_name: &str) -> Result<Vec<Event>, RepoError> {
+    async fn find_attendees(
+        &self,
+        event_id: EventId,
+        states: &[RsvpState],
+        sort: AttendeeSort,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<Attendee>, u32), RepoError> {
+        let skip = skip(page, limit);
+        let order_by = match sort {
+            AttendeeSort::RegisteredAt => "r.registeredAt, u.name",
+            AttendeeSort::Name => "u.name",
+        };
+
+        let total = count(
+            &self.graph,
+            query(
+                "MATCH (:User)-[r:REGISTERED_TO]->(:Event {id: $id})
+                  WHERE coalesce(r.state, 'going') IN $states
+                  RETURN count(r) AS total",
+            )
+            .param("id", event_id)
+            .param("states", RsvpState::names(states)),
+        )
+        .await
+        .map_err(|e| Other(e.to_string()))?;
+
         let mut rows = self
             .graph
             .execute(
-                query(
-                    "\
-                MATCH (u:User {name: $n})\
-                MATCH (u)-[:REGISTERED_TO]->(e:Event)\
-                OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)\
-                RETURN
-                   e.id               AS eventId,
-                   e.name             AS eventName,
-                   e.startDatetime    AS start,
-                   collect(k.name)    AS keywords;\
-                ",
-                )
-                .param("n", user_name),
+                query(&format!(
+                    "MATCH (u:User)-[r:REGISTERED_TO]->(e:Event {{id: $id}})
+                      WHERE coalesce(r.state, 'going') IN $states
+                      RETURN u.name AS name,
+                             coalesce(r.state, 'going') AS state,
+                             r.registeredAt AS registeredAt
+                      ORDER BY {}
+                      SKIP $skip LIMIT $limit",
+                    order_by
+                ))
+                .param("id", event_id)
+                .param("states", RsvpState::names(states))
+                .param("skip", skip)
+                .param("limit", limit as i64),
+            )
+            .await
+            .map_err(|e| Other(e.to_string()))?;
+
+        let mut attendees = Vec::<Attendee>::new();
+
+        while let Some(row) = rows.next().await.map_err(|e| Other(e.to_string()))? {
+            let state: String = row.get("state").map_err(|e| Other(e.to_string()))?;
+            attendees.push(Attendee {
+                name: row.get("name").map_err(|e| Other(e.to_string()))?,
+                state: RsvpState::from_name(&state).ok_or_else(|| Other(format!("Unknown RSVP state: {}", state)))?,
+                registered_at: row.get("registeredAt").map_err(|e| Other(e.to_string()))?,
+            });
+        }
+
+        Ok((attendees, total))
+    }
+
+    async fn find_all_events_of_user(
+        &self,
+        user_name: &str,
+        states: &[RsvpState],
+    ) -> Result<Vec<Event>, RepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                event_query(EVENTS_OF_USER)
+                    .param("n", user_name)
+                    .param("states", RsvpState::names(states)),
             )
             .await
             .map_err(|e| Other(e.to_string()))?;
@@ -92,15 +340,16 @@ impl UserEventRepository {
         Ok(events)
     }
 
-    pub async fn recommend_events_for_user_based_on_events_similarity(
+    async fn recommend_events_for_user_based_on_events_similarity(
         &self,
         user_name: &str,
     ) -> Result<Vec<Event>, RepoError> {
         let mut rows = self.graph.execute(
-            query(
+            event_query(
                 "\
-                MATCH (u:User {name: $n})-[:REGISTERED_TO]->(e:Event)-[:HAS]->(k:EventKeyword)<-[:HAS]-(other:Event WHERE other.startDatetime > datetime())
-                WHERE NOT EXISTS((u)-[:REGISTERED_TO]->(other))
+                MATCH (u:User {name: $n})-[r:REGISTERED_TO]->(e:Event)-[:HAS]->(k:EventKeyword)<-[:HAS]-(other:Event WHERE other.startDatetime > datetime() AND other.archivedAt IS NULL)
+                WHERE coalesce(r.state, 'going') IN $positiveStates
+                  AND NOT EXISTS((u)-[:REGISTERED_TO]->(other))
                 WITH e, other, count(k) AS intersection
                 WITH e, other, intersection,
                   [(e)-[:HAS]->(ek:EventKeyword) | ek.name] AS set1,
@@ -111,13 +360,13 @@ impl UserEventRepository {
                 WITH other AS e, jaccard, set2 AS keywords
                 WHERE jaccard > 0.5
                 RETURN
-                   e.id               AS eventId,
-                   e.name             AS eventName,
-                   e.startDatetime    AS start,
+                   {event_columns},
                    keywords
                 ORDER BY jaccard DESC;
                 "
-            ).param("n", user_name)
+            )
+            .param("n", user_name)
+            .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
         ).await.map_err(|e| Other(e.to_string()))?;
         let mut events = Vec::<Event>::new();
         while let Some(row) = match rows.next().await {
@@ -130,25 +379,29 @@ impl UserEventRepository {
         Ok(events)
     }
 
-    pub async fn recommend_events_for_user_based_on_users_similarity(
+    async fn recommend_events_for_user_based_on_users_similarity(
         &self,
         user_name: &str,
     ) -> Result<Vec<Event>, RepoError> {
         let mut rows = self.graph.execute(
-            query(
+            event_query(
                 "
                 MATCH (u:User {name: $n})-[r:REGISTERED_TO]->(e:Event)
-                WITH COLLECT(e) AS events, u
-                MATCH (u)-[s:SIMILAR]->(:User)-[:REGISTERED_TO]->(ee:Event WHERE ee.startDatetime > datetime() AND (NOT ee  IN events))
-                WITH DISTINCT ee
-                OPTIONAL MATCH (ee)-[:HAS]->(k:EventKeyword)
+                WITH u,
+                     COLLECT(e) AS events,
+                     COLLECT(CASE WHEN coalesce(r.state, 'going') IN $positiveStates THEN e END) AS liked
+                WHERE size(liked) > 0
+                MATCH (u)-[s:SIMILAR]->(:User)-[sr:REGISTERED_TO]->(ee:Event WHERE ee.startDatetime > datetime() AND ee.archivedAt IS NULL AND (NOT ee  IN events))
+                WHERE coalesce(sr.state, 'going') IN $positiveStates
+                WITH DISTINCT ee AS e
+                OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
                 RETURN
-                   ee.id               AS eventId,
-                   ee.name             AS eventName,
-                   ee.startDatetime    AS start,
+                   {event_columns},
                    collect(k.name)    AS keywords;
                 "
-            ).param("n", user_name)
+            )
+            .param("n", user_name)
+            .param("positiveStates", RsvpState::names(RsvpState::POSITIVE))
         ).await.map_err(|e| Other(e.to_string()))?;
         let mut events = Vec::<Event>::new();
         while let Some(row) = match rows.next().await {
@@ -161,17 +414,18 @@ impl UserEventRepository {
         Ok(events)
     }
 
-    pub async fn is_user_registered_to_event(
+    async fn find_registration_state(
         &self,
         user_name: &str,
-        event_id: u16,
-    ) -> Result<bool, RepoError> {
+        event_id: EventId,
+    ) -> Result<Option<RsvpState>, RepoError> {
         let mut rows = self
             .graph
             .execute(
                 query(
                     "MATCH (u:User {name: $n}), (e:Event {id: $id})
-                    RETURN EXISTS((u)-[:REGISTERED_TO]->(e)) AS isAttending",
+                    OPTIONAL MATCH (u)-[r:REGISTERED_TO]->(e)
+                    RETURN CASE WHEN r IS NULL THEN null ELSE coalesce(r.state, 'going') END AS state",
                 )
                 .param("n", user_name)
                 .param("id", event_id),
@@ -186,9 +440,132 @@ impl UserEventRepository {
             Err(e) => return Err(Other(e.to_string())),
         };
 
-        // Extract the boolean value from the "isAttending" column
-        let is_attending: bool = row.get("isAttending").map_err(|e| Other(e.to_string()))?;
+        // Extract the state from the "state" column
+        let state: Option<String> = row.get("state").map_err(|e| Other(e.to_string()))?;
+
+        state
+            .map(|state| RsvpState::from_name(&state).ok_or_else(|| Other(format!("Unknown RSVP state: {}", state))))
+            .transpose()
+    }
+
+    async fn find_events_organized_by_user(&self, user_name: &str) -> Result<Vec<Event>, RepoError> {
+        let mut rows = self
+            .graph
+            .execute(
+                event_query(
+                    "\
+                MATCH (u:User {name: $n})-[:ORGANIZES]->(e:Event)
+                WHERE e.archivedAt IS NULL
+                OPTIONAL MATCH (e)-[:HAS]->(k:EventKeyword)
+                RETURN
+                   {event_columns},
+                   collect(k.name)    AS keywords;
+                ",
+                )
+                .param("n", user_name),
+            )
+            .await
+            .map_err(|e| Other(e.to_string()))?;
+
+        let mut events = Vec::<Event>::new();
+
+        while let Some(row) = match rows.next().await {
+            Ok(r) => r,
+            Err(e) => return Err(Other(e.to_string())),
+        } {
+            let event: Event = Event::from_row(&row).map_err(|e| Other(e.to_string()))?;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    async fn add_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError> {
+        self.graph
+            .run(
+                query(
+                    "\
+                MATCH (u:User {name: $n})
+                MATCH (e:Event {id: $id})
+                MERGE (u)-[r:ORGANIZES]->(e)
+                  ON CREATE SET r.primary = false
+                ",
+                )
+                .param("n", user_name)
+                .param("id", event_id),
+            )
+            .await
+            .map_err(|e| Other(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn remove_co_organizer(&self, user_name: &str, event_id: EventId) -> Result<(), RepoError> {
+        self.graph
+            .run(
+                query(
+                    "\
+                MATCH (u:User {name: $n})-[r:ORGANIZES {primary: false}]->(e:Event {id: $id})
+                DELETE r
+                ",
+                )
+                .param("n", user_name)
+                .param("id", event_id),
+            )
+            .await
+            .map_err(|e| Other(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::neo4j::Neo4jConnection;
+    use crate::repo::events::with_event_columns;
+    use std::env;
+
+    const CLAUSES: &[&str] = &[
+        "MATCH", "OPTIONAL", "WHERE", "WITH", "RETURN", "ORDER", "SKIP", "LIMIT", "UNWIND", "CALL", "MERGE", "CREATE",
+        "SET", "REMOVE", "DELETE", "FOREACH",
+    ];
+
+    /// Words ending in a clause keyword glued to what comes before it, like the
+    /// `NULLOPTIONAL` a `\` line continuation leaves by eating the newline.
+    fn glued_clauses(cypher: &str) -> Vec<&str> {
+        cypher
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .filter(|word| CLAUSES.iter().any(|clause| word.len() > clause.len() && word.ends_with(clause)))
+            .collect()
+    }
+
+    #[test]
+    fn events_of_user_keeps_its_clauses_apart() {
+        assert!(glued_clauses("WHERE e.archivedAt IS NULLOPTIONAL MATCH (e)").contains(&"NULLOPTIONAL"));
+        assert_eq!(glued_clauses(&with_event_columns(EVENTS_OF_USER)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn events_of_user_filters_archived_events_on_their_own_line() {
+        let filter = EVENTS_OF_USER
+            .lines()
+            .find(|line| line.trim_start().starts_with("WHERE"))
+            .expect("the query has a WHERE clause");
+        assert!(filter.trim_end().ends_with("AND e.archivedAt IS NULL"));
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a Neo4j database at DB_URI"]
+    async fn events_of_user_runs_on_neo4j() {
+        let neo4j = Neo4jConnection::new(
+            &env::var("DB_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string()),
+            &env::var("DB_USER").unwrap_or_else(|_| "neo4j".to_string()),
+            &env::var("DB_PASSWORD").expect("DB_PASSWORD not set"),
+        )
+        .await
+        .unwrap();
+        let repo = Neo4jUserEventRepository::new(neo4j.graph);
 
-        Ok(is_attending)
+        let events = repo.find_all_events_of_user("nobody", RsvpState::ALL).await.unwrap();
+        assert!(events.is_empty());
     }
 }
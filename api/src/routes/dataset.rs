@@ -0,0 +1,65 @@
+use crate::models::dataset::ExportFile;
+use crate::models::user::Role;
+use crate::services::dataset::DatasetService;
+use crate::utils::api_response::ApiResponse;
+use crate::utils::auth::AuthenticatedUser;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::Responder;
+use rocket::{Request, Response, Route, State};
+use std::io::Cursor;
+use std::sync::Arc;
+
+pub struct DatasetController {
+    dataset_service: Arc<DatasetService>,
+}
+
+impl DatasetController {
+    pub fn new(dataset_service: Arc<DatasetService>) -> Self {
+        Self { dataset_service }
+    }
+
+    pub fn routes() -> Vec<Route> {
+        routes![export]
+    }
+}
+
+/// An export file sent as an attachment under its own name.
+pub struct Download {
+    file: ExportFile,
+    content: Vec<u8>,
+}
+
+impl<'r> Responder<'r, 'static> for Download {
+    fn respond_to(self, _: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let content_type = match self.file {
+            ExportFile::UserEvents | ExportFile::EventKeywords | ExportFile::SimilarUsers => ContentType::CSV,
+            ExportFile::JsonLines => ContentType::new("application", "x-ndjson"),
+            ExportFile::GraphMl => ContentType::new("application", "graphml+xml"),
+        };
+
+        Response::build()
+            .header(content_type)
+            .header(Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.file.file_name()),
+            ))
+            .sized_body(self.content.len(), Cursor::new(self.content))
+            .ok()
+    }
+}
+
+/// `file` is one of `user_events.csv`, `event_keyword.csv`, `similar_users.csv`,
+/// `graph.jsonl` and `graph.graphml`.
+#[get("/export/<file>")]
+async fn export(
+    controller: &State<DatasetController>,
+    auth: AuthenticatedUser,
+    file: &str,
+) -> Result<Download, ApiResponse<String>> {
+    auth.check_role(&[Role::Admin])?;
+
+    let file = ExportFile::from_file_name(file)
+        .ok_or_else(|| ApiResponse::message_only(format!("Unknown export file: {}", file), Status::NotFound))?;
+    let content = controller.dataset_service.export(file).await?;
+    Ok(Download { file, content })
+}
@@ -1,12 +1,25 @@
-use crate::models::event::{Event, EventUpdate};
+use crate::models::event::{
+    Event, EventId, EventListParams, EventPatch, EventPeriod, EventSearchHit, EventSort, EventUpdate, FeaturedUpdate,
+    KeywordFilter, KeywordMatch, SortOrder,
+};
+use crate::models::keyword::{KeywordSort, KeywordStats};
+use crate::models::registration::{Attendee, AttendeeSort, RsvpState};
+use crate::models::revision::Revision;
+use crate::models::user::Role;
 use rocket::State;
 use std::sync::Arc;
 
 use crate::services::events::EventService;
 use crate::services::users_events::UserEventService;
-use crate::utils::api_response::ApiResponse;
+use crate::utils::api_response::{ApiResponse, PaginatedItemsResponse};
+use crate::utils::auth::AuthenticatedUser;
+use crate::utils::etag::{IfMatch, Tagged};
+use crate::utils::pagination::PaginationParams;
 use rocket::serde::json::Json;
 
+/// Roles allowed to create events. Editing and deleting is up to the event's organizers.
+const EVENT_MANAGERS: &[Role] = &[Role::Admin, Role::Organizer];
+
 pub struct EventController {
     event_service: Arc<EventService>,
     user_event_service: Arc<UserEventService>,
@@ -29,82 +42,238 @@ impl EventController {
             get_one,
             add,
             delete,
+            restore,
+            get_history,
+            rollback,
+            get_archived,
             edit,
+            patch,
             assign_user_to_event,
             unassign_user_from_event,
+            add_co_organizer,
+            remove_co_organizer,
             get_featured,
-            is_attendees_to_event,
+            pin_featured,
+            unpin_featured,
+            get_attendees,
+            get_attendee_state,
             get_events_by_keywords,
-            get_events_keywords
+            get_events_keywords,
+            get_keyword_stats,
+            search_events
         ]
     }
 }
 
-#[get("/events")]
-async fn get_all(controller: &State<EventController>) -> ApiResponse<Vec<Event>> {
-    controller.event_service.get_events().await
+#[get("/events?<sort>&<order>&<period>&<from>&<to>&<pagination..>")]
+async fn get_all(
+    controller: &State<EventController>,
+    sort: Option<EventSort>,
+    order: Option<SortOrder>,
+    period: Option<EventPeriod>,
+    from: Option<String>,
+    to: Option<String>,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<Event> {
+    let params = EventListParams { sort, order, period, from, to };
+    controller.event_service.get_events(params, pagination).await
 }
 
 #[get("/event/<id>")]
-async fn get_one(controller: &State<EventController>, id: u16) -> ApiResponse<Event> {
-    controller.event_service.get_event(id).await
+async fn get_one(controller: &State<EventController>, id: EventId) -> Tagged<Event> {
+    Tagged::new(controller.event_service.get_event(id).await, Event::etag)
 }
 
 #[post("/event", format = "application/json", data = "<event>")]
-async fn add(controller: &State<EventController>, event: Json<EventUpdate>) -> ApiResponse<Event> {
-    controller.event_service.add_event(event.into_inner()).await
+async fn add(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    event: Json<EventUpdate>,
+) -> ApiResponse<Event> {
+    if let Err(response) = auth.check_role(EVENT_MANAGERS) {
+        return response;
+    }
+
+    controller.event_service.add_event(event.into_inner(), &auth.name).await
 }
 
 #[delete("/event/<id>")]
-async fn delete(controller: &State<EventController>, id: u16) -> ApiResponse<String> {
-    controller.event_service.remove_event(id).await
+async fn delete(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    id: EventId,
+) -> ApiResponse<String> {
+    controller.event_service.remove_event(id, &auth).await
+}
+
+#[post("/event/<id>/restore")]
+async fn restore(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    id: EventId,
+) -> ApiResponse<Event> {
+    controller.event_service.restore_event(id, &auth).await
+}
+
+#[get("/event/<id>/history?<pagination..>")]
+async fn get_history(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    id: EventId,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<Revision> {
+    controller.event_service.get_event_history(id, &auth, pagination).await
+}
+
+#[post("/event/<id>/history/<revision>/rollback")]
+async fn rollback(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    id: EventId,
+    revision: i64,
+) -> Tagged<Event> {
+    if let Err(response) = auth.check_role(&[Role::Admin]) {
+        return Tagged::new(response, Event::etag);
+    }
+
+    Tagged::new(controller.event_service.rollback_event(id, revision, &auth).await, Event::etag)
+}
+
+#[get("/events/archived?<pagination..>")]
+async fn get_archived(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<Event> {
+    controller.event_service.get_archived_events(&auth, pagination).await
 }
 
 #[put("/event/<id>", format = "application/json", data = "<event>")]
 async fn edit(
     controller: &State<EventController>,
-    id: u16,
+    auth: AuthenticatedUser,
+    if_match: IfMatch,
+    id: EventId,
     event: Json<EventUpdate>,
-) -> ApiResponse<Event> {
-    controller
+) -> Tagged<Event> {
+    let response = controller
         .event_service
-        .edit_event(id, event.into_inner())
-        .await
+        .edit_event(id, event.into_inner(), &auth, &if_match)
+        .await;
+    Tagged::new(response, Event::etag)
 }
 
-#[put("/events/<event_id>/attendees/<user_name>")]
+#[patch("/event/<id>", format = "application/json", data = "<event>")]
+async fn patch(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    if_match: IfMatch,
+    id: EventId,
+    event: Json<EventPatch>,
+) -> Tagged<Event> {
+    let response = controller
+        .event_service
+        .patch_event(id, event.into_inner(), &auth, &if_match)
+        .await;
+    Tagged::new(response, Event::etag)
+}
+
+#[put("/events/<event_id>/attendees/<user_name>?<state>&<force>&<waitlist>")]
 async fn assign_user_to_event(
     controller: &State<EventController>,
-    event_id: u16,
+    auth: AuthenticatedUser,
+    event_id: EventId,
     user_name: &str,
+    state: Option<RsvpState>,
+    force: Option<bool>,
+    waitlist: Option<bool>,
 ) -> ApiResponse<String> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
     controller
         .user_event_service
-        .assign_user_to_event(user_name, event_id)
+        .assign_user_to_event(
+            user_name,
+            event_id,
+            state.unwrap_or_default(),
+            force.unwrap_or(false),
+            waitlist.unwrap_or(true),
+        )
         .await
 }
 
 #[delete("/events/<event_id>/attendees/<user_name>")]
 async fn unassign_user_from_event(
     controller: &State<EventController>,
-    event_id: u16,
+    auth: AuthenticatedUser,
+    event_id: EventId,
     user_name: &str,
 ) -> ApiResponse<String> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
     controller
         .user_event_service
         .unassign_user_from_event(user_name, event_id)
         .await
 }
 
+#[put("/events/<event_id>/organizers/<user_name>")]
+async fn add_co_organizer(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    event_id: EventId,
+    user_name: &str,
+) -> ApiResponse<String> {
+    controller
+        .user_event_service
+        .add_co_organizer(user_name, event_id, &auth)
+        .await
+}
+
+#[delete("/events/<event_id>/organizers/<user_name>")]
+async fn remove_co_organizer(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    event_id: EventId,
+    user_name: &str,
+) -> ApiResponse<String> {
+    controller
+        .user_event_service
+        .remove_co_organizer(user_name, event_id, &auth)
+        .await
+}
+
+/// Lists users who RSVP'd to the event with one of the `state` values, every state
+/// but `declined` when none is given.
+#[get("/events/<event_id>/attendees?<state>&<sort>&<pagination..>")]
+async fn get_attendees(
+    controller: &State<EventController>,
+    event_id: EventId,
+    state: Vec<RsvpState>,
+    sort: Option<AttendeeSort>,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<Attendee> {
+    let states = if state.is_empty() { RsvpState::INTERESTED } else { &state };
+
+    controller
+        .user_event_service
+        .find_attendees(event_id, states, sort.unwrap_or_default(), pagination)
+        .await
+}
+
 #[get("/events/<event_id>/attendees/<user_name>")]
-async fn is_attendees_to_event(
+async fn get_attendee_state(
     controller: &State<EventController>,
     user_name: &str,
-    event_id: u16,
-) -> ApiResponse<bool> {
+    event_id: EventId,
+) -> ApiResponse<Option<RsvpState>> {
     controller
         .user_event_service
-        .is_user_registered_to_event(user_name, event_id)
+        .get_registration_state(user_name, event_id)
         .await
 }
 
@@ -113,12 +282,85 @@ async fn get_featured(controller: &State<EventController>) -> ApiResponse<Vec<Ev
     controller.event_service.get_featured_events().await
 }
 
-#[get("/events/filter?<keyword>")]
-async fn get_events_by_keywords(controller: &State<EventController>, keyword: Vec<String>) -> ApiResponse<Vec<Event>> {
-    controller.event_service.get_events_by_keywords(keyword).await
+#[put("/events/<event_id>/featured", format = "application/json", data = "<featured>")]
+async fn pin_featured(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    event_id: EventId,
+    featured: Json<FeaturedUpdate>,
+) -> ApiResponse<Event> {
+    if let Err(response) = auth.check_role(&[Role::Admin]) {
+        return response;
+    }
+
+    controller
+        .event_service
+        .set_featured(event_id, Some(featured.into_inner()))
+        .await
+}
+
+#[delete("/events/<event_id>/featured")]
+async fn unpin_featured(
+    controller: &State<EventController>,
+    auth: AuthenticatedUser,
+    event_id: EventId,
+) -> ApiResponse<Event> {
+    if let Err(response) = auth.check_role(&[Role::Admin]) {
+        return response;
+    }
+
+    controller.event_service.set_featured(event_id, None).await
+}
+
+/// `keyword` and `not` can be repeated, e.g. `?keyword=sport&keyword=music&mode=any&not=online`.
+#[get("/events/filter?<keyword>&<not>&<mode>&<sort>&<order>&<period>&<from>&<to>&<pagination..>")]
+#[allow(clippy::too_many_arguments)]
+async fn get_events_by_keywords(
+    controller: &State<EventController>,
+    keyword: Vec<String>,
+    not: Vec<String>,
+    mode: Option<KeywordMatch>,
+    sort: Option<EventSort>,
+    order: Option<SortOrder>,
+    period: Option<EventPeriod>,
+    from: Option<String>,
+    to: Option<String>,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<Event> {
+    let keywords = KeywordFilter::new(keyword, not, mode.unwrap_or_default());
+    let params = EventListParams { sort, order, period, from, to };
+    controller
+        .event_service
+        .get_events_by_keywords(keywords, params, pagination)
+        .await
 }
 
 #[get("/events/keywords")]
 async fn get_events_keywords(controller: &State<EventController>) -> ApiResponse<Vec<String>> {
     controller.event_service.get_events_keywords().await
-}
\ No newline at end of file
+}
+
+#[get("/events/keywords/stats?<sort>&<order>&<pagination..>")]
+async fn get_keyword_stats(
+    controller: &State<EventController>,
+    sort: Option<KeywordSort>,
+    order: Option<SortOrder>,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<KeywordStats> {
+    controller
+        .event_service
+        .get_keyword_stats(sort.unwrap_or_default(), order, pagination)
+        .await
+}
+
+#[get("/events/search?<q>&<pagination..>")]
+async fn search_events(
+    controller: &State<EventController>,
+    q: Option<&str>,
+    pagination: PaginationParams,
+) -> PaginatedItemsResponse<EventSearchHit> {
+    controller
+        .event_service
+        .search_events(q.unwrap_or_default(), pagination)
+        .await
+}
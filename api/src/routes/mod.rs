@@ -1,2 +1,3 @@
+pub mod dataset;
 pub mod events;
-pub mod users;
+pub mod users;
\ No newline at end of file
@@ -1,10 +1,12 @@
 use crate::models::event::Event;
-use crate::models::user::User;
+use crate::models::registration::RsvpState;
+use crate::models::user::{Credentials, PasswordUpdate, Role, RoleUpdate, User, UserUpdate};
 use crate::services::users::UserService;
 use crate::services::users_events::UserEventService;
 use crate::utils::api_response::{ApiResponse, PaginatedItemsResponse};
+use crate::utils::auth::{AuthToken, AuthenticatedUser};
 use crate::utils::pagination::PaginationParams;
-use neo4rs::Graph;
+use rocket::serde::json::Json;
 use rocket::{Route, State};
 use std::sync::Arc;
 
@@ -25,7 +27,14 @@ impl UserController {
         routes![
             get_one,
             get_all,
+            create,
+            update,
+            delete,
+            login,
+            set_password,
+            set_role,
             get_all_events_of_user,
+            get_events_organized_by_user,
             recommend_events_for_user_based_on_events_similarity,
             recommend_events_for_user_based_on_users_similarity,
         ]
@@ -37,30 +46,132 @@ pub async fn get_one(controller: &State<UserController>, user_name: &str) -> Api
     controller.user_service.get_one(user_name).await
 }
 
+#[post("/user", format = "application/json", data = "<user>")]
+pub async fn create(controller: &State<UserController>, user: Json<UserUpdate>) -> ApiResponse<User> {
+    controller.user_service.create_user(user.into_inner()).await
+}
+
+#[put("/user/<user_name>", format = "application/json", data = "<user>")]
+pub async fn update(
+    controller: &State<UserController>,
+    auth: AuthenticatedUser,
+    user_name: &str,
+    user: Json<UserUpdate>,
+) -> ApiResponse<User> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
+    controller
+        .user_service
+        .update_user(user_name, user.into_inner())
+        .await
+}
+
+#[delete("/user/<user_name>")]
+pub async fn delete(
+    controller: &State<UserController>,
+    auth: AuthenticatedUser,
+    user_name: &str,
+) -> ApiResponse<String> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
+    controller.user_service.delete_user(user_name).await
+}
+
+#[put("/user/<user_name>/role", format = "application/json", data = "<role>")]
+pub async fn set_role(
+    controller: &State<UserController>,
+    auth: AuthenticatedUser,
+    user_name: &str,
+    role: Json<RoleUpdate>,
+) -> ApiResponse<User> {
+    if let Err(response) = auth.check_role(&[Role::Admin]) {
+        return response;
+    }
+
+    controller
+        .user_service
+        .set_role(user_name, role.into_inner().role)
+        .await
+}
+
+/// Sets a user's password. Admins can set anyone's, which gives users imported without a
+/// password a way to log in.
+#[put("/user/<user_name>/password", format = "application/json", data = "<password>")]
+pub async fn set_password(
+    controller: &State<UserController>,
+    auth: AuthenticatedUser,
+    user_name: &str,
+    password: Json<PasswordUpdate>,
+) -> ApiResponse<User> {
+    if auth.role != Role::Admin
+        && let Err(response) = auth.check_is(user_name)
+    {
+        return response;
+    }
+
+    controller
+        .user_service
+        .set_password(user_name, password.into_inner())
+        .await
+}
+
+#[post("/login", format = "application/json", data = "<credentials>")]
+pub async fn login(
+    controller: &State<UserController>,
+    credentials: Json<Credentials>,
+) -> ApiResponse<AuthToken> {
+    controller.user_service.login(credentials.into_inner()).await
+}
+
 #[get("/users?<pagination..>")]
 pub async fn get_all(
-    pagination: Option<PaginationParams>,
+    pagination: PaginationParams,
     controller: &State<UserController>,
 ) -> PaginatedItemsResponse<User> {
     controller.user_service.get_all(pagination).await
 }
 
-#[get("/user/<user_name>/events")]
+/// Lists events the user RSVP'd to with one of the `state` values, every state but
+/// `declined` when none is given.
+#[get("/user/<user_name>/events?<state>")]
 pub async fn get_all_events_of_user(
     controller: &State<UserController>,
     user_name: &str,
+    state: Vec<RsvpState>,
+) -> ApiResponse<Vec<Event>> {
+    let states = if state.is_empty() { RsvpState::INTERESTED } else { &state };
+
+    controller
+        .user_event_service
+        .find_all_events_of_user(user_name, states)
+        .await
+}
+
+#[get("/user/<user_name>/organized")]
+pub async fn get_events_organized_by_user(
+    controller: &State<UserController>,
+    user_name: &str,
 ) -> ApiResponse<Vec<Event>> {
     controller
         .user_event_service
-        .find_all_events_of_user(user_name)
+        .find_events_organized_by_user(user_name)
         .await
 }
 
 #[get("/user/<user_name>/recommendations/1")]
 pub async fn recommend_events_for_user_based_on_events_similarity(
     controller: &State<UserController>,
+    auth: AuthenticatedUser,
     user_name: &str,
 ) -> ApiResponse<Vec<Event>> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
     controller
         .user_event_service
         .recommend_events_for_user_based_on_events_similarity(user_name)
@@ -70,8 +181,13 @@ pub async fn recommend_events_for_user_based_on_events_similarity(
 #[get("/user/<user_name>/recommendations/2")]
 pub async fn recommend_events_for_user_based_on_users_similarity(
     controller: &State<UserController>,
+    auth: AuthenticatedUser,
     user_name: &str,
 ) -> ApiResponse<Vec<Event>> {
+    if let Err(response) = auth.check_is(user_name) {
+        return response;
+    }
+
     controller
         .user_event_service
         .recommend_events_for_user_based_on_users_similarity(user_name)
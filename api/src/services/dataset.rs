@@ -0,0 +1,357 @@
+use crate::models::dataset::{
+    EVENT_KEYWORD_COLUMNS, EventKeywordRow, EventRecord, ExportFile, GraphDump, RegistrationRecord, SIMILAR_USERS_COLUMNS,
+    SimilarityRecord, USER_EVENTS_COLUMNS,
+};
+use crate::models::event::EventId;
+use crate::models::user::User;
+use crate::repo::dataset::DatasetRepository;
+use crate::repo::{ApiError, RepoError};
+use crate::utils::api_response::ApiResponse;
+use rocket::http::Status;
+use rocket::serde::json::serde_json;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+pub struct DatasetService {
+    dataset_repo: Box<dyn DatasetRepository>,
+}
+
+impl DatasetService {
+    pub fn new(dataset_repo: Box<dyn DatasetRepository>) -> Self {
+        Self { dataset_repo }
+    }
+
+    pub async fn dump(&self) -> Result<GraphDump, RepoError> {
+        self.dataset_repo.dump().await
+    }
+
+    /// The content of one export file, built from a fresh dump of the graph.
+    pub async fn export(&self, file: ExportFile) -> Result<Vec<u8>, ApiResponse<String>> {
+        let dump = self
+            .dataset_repo
+            .dump()
+            .await
+            .map_err(|e| ApiResponse::message_only(format!("{}", e), e.status()))?;
+
+        let mut content = Vec::new();
+        write_export(&dump, file, &mut content)
+            .map_err(|e| ApiResponse::message_only(format!("Can't export {}: {}", file.file_name(), e), Status::InternalServerError))?;
+        Ok(content)
+    }
+}
+
+/// Writes `file` of the export of `dump` to `out`.
+pub fn write_export(dump: &GraphDump, file: ExportFile, out: impl Write) -> io::Result<()> {
+    match file {
+        ExportFile::UserEvents => write_user_events(dump, out),
+        ExportFile::EventKeywords => write_event_keywords(dump, out),
+        ExportFile::SimilarUsers => write_similar_users(dump, out),
+        ExportFile::JsonLines => write_json_lines(dump, out),
+        ExportFile::GraphMl => write_graphml(dump, out),
+    }
+}
+
+/// One row per registration, users and events without any are left out like in the
+/// files `import.cypher` reads. The layout has no RSVP state or archive date and every
+/// imported row becomes a `Going` registration to an active event, so only positive
+/// registrations to events that aren't archived are written.
+fn write_user_events(dump: &GraphDump, out: impl Write) -> io::Result<()> {
+    let events: HashMap<EventId, &EventRecord> = dump
+        .events
+        .iter()
+        .filter(|event| event.archived_at.is_none())
+        .map(|event| (event.id, event))
+        .collect();
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(USER_EVENTS_COLUMNS)?;
+
+    for registration in dump.registrations.iter().filter(|registration| registration.state.is_positive()) {
+        if let Some(event) = events.get(&registration.event_id) {
+            writer.write_record([
+                registration.user_name.as_str(),
+                &event.id.to_string(),
+                &event.name,
+                &event.start_datetime.to_rfc3339(),
+            ])?;
+        }
+    }
+    writer.flush()
+}
+
+fn write_event_keywords(dump: &GraphDump, out: impl Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(EVENT_KEYWORD_COLUMNS)?;
+
+    for row in &dump.event_keywords {
+        writer.write_record([row.event_id.to_string().as_str(), &row.keyword])?;
+    }
+    writer.flush()
+}
+
+fn write_similar_users(dump: &GraphDump, out: impl Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(SIMILAR_USERS_COLUMNS)?;
+
+    for similarity in &dump.similar {
+        let score = similarity.score.map(|score| score.to_string()).unwrap_or_default();
+        writer.write_record([similarity.user_name.as_str(), &similarity.similar_user_name, &score])?;
+    }
+    writer.flush()
+}
+
+/// A line of the JSON Lines export, tagged with the node label or relationship type.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonLine<'a> {
+    User(&'a User),
+    Event(&'a EventRecord),
+    Keyword { name: &'a str },
+    RegisteredTo(&'a RegistrationRecord),
+    Has(&'a EventKeywordRow),
+    Similar(&'a SimilarityRecord),
+}
+
+/// Nodes first, then relationships, one JSON object per line.
+fn write_json_lines(dump: &GraphDump, out: impl Write) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    let lines = dump
+        .users
+        .iter()
+        .map(JsonLine::User)
+        .chain(dump.events.iter().map(JsonLine::Event))
+        .chain(dump.keywords.iter().map(|name| JsonLine::Keyword { name }))
+        .chain(dump.registrations.iter().map(JsonLine::RegisteredTo))
+        .chain(dump.event_keywords.iter().map(JsonLine::Has))
+        .chain(dump.similar.iter().map(JsonLine::Similar));
+
+    for line in lines {
+        serde_json::to_writer(&mut out, &line)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+/// `(id, for, type)` of the GraphML attributes. Node IDs are prefixed with `u:`, `e:` or
+/// `k:` as users and keywords are identified by name and events by number.
+const GRAPHML_KEYS: &[(&str, &str, &str)] = &[
+    ("labels", "node", "string"),
+    ("name", "node", "string"),
+    ("role", "node", "string"),
+    ("event_id", "node", "long"),
+    ("start_datetime", "node", "string"),
+    ("end_datetime", "node", "string"),
+    ("timezone", "node", "string"),
+    ("capacity", "node", "long"),
+    ("archived_at", "node", "string"),
+    ("label", "edge", "string"),
+    ("state", "edge", "string"),
+    ("registered_at", "edge", "string"),
+    ("score", "edge", "double"),
+];
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Writes a `<data>` element, nothing when the value is unset.
+fn write_data(out: &mut impl Write, key: &str, value: Option<impl Display>) -> io::Result<()> {
+    match value {
+        Some(value) => writeln!(out, "      <data key=\"{}\">{}</data>", key, xml_escape(&value.to_string())),
+        None => Ok(()),
+    }
+}
+
+fn write_graphml(dump: &GraphDump, out: impl Write) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    for (id, scope, kind) in GRAPHML_KEYS {
+        writeln!(out, r#"  <key id="{id}" for="{scope}" attr.name="{id}" attr.type="{kind}"/>"#)?;
+    }
+    writeln!(out, r#"  <graph id="events" edgedefault="directed">"#)?;
+
+    for user in &dump.users {
+        writeln!(out, r#"    <node id="u:{}">"#, xml_escape(&user.name))?;
+        write_data(&mut out, "labels", Some("User"))?;
+        write_data(&mut out, "name", Some(&user.name))?;
+        write_data(&mut out, "role", Some(user.role.as_str()))?;
+        writeln!(out, "    </node>")?;
+    }
+    for event in &dump.events {
+        writeln!(out, r#"    <node id="e:{}">"#, event.id)?;
+        write_data(&mut out, "labels", Some("Event"))?;
+        write_data(&mut out, "name", Some(&event.name))?;
+        write_data(&mut out, "event_id", Some(event.id))?;
+        write_data(&mut out, "start_datetime", Some(event.start_datetime.to_rfc3339()))?;
+        write_data(&mut out, "end_datetime", event.end_datetime.map(|end| end.to_rfc3339()))?;
+        write_data(&mut out, "timezone", event.timezone.as_ref())?;
+        write_data(&mut out, "capacity", event.capacity)?;
+        write_data(&mut out, "archived_at", event.archived_at.map(|archived_at| archived_at.to_rfc3339()))?;
+        writeln!(out, "    </node>")?;
+    }
+    for keyword in &dump.keywords {
+        writeln!(out, r#"    <node id="k:{}">"#, xml_escape(keyword))?;
+        write_data(&mut out, "labels", Some("EventKeyword"))?;
+        write_data(&mut out, "name", Some(keyword))?;
+        writeln!(out, "    </node>")?;
+    }
+
+    for registration in &dump.registrations {
+        writeln!(
+            out,
+            r#"    <edge source="u:{}" target="e:{}">"#,
+            xml_escape(&registration.user_name),
+            registration.event_id
+        )?;
+        write_data(&mut out, "label", Some("REGISTERED_TO"))?;
+        write_data(&mut out, "state", Some(registration.state.as_str()))?;
+        write_data(&mut out, "registered_at", registration.registered_at.map(|at| at.to_rfc3339()))?;
+        writeln!(out, "    </edge>")?;
+    }
+    for row in &dump.event_keywords {
+        writeln!(out, r#"    <edge source="e:{}" target="k:{}">"#, row.event_id, xml_escape(&row.keyword))?;
+        write_data(&mut out, "label", Some("HAS"))?;
+        writeln!(out, "    </edge>")?;
+    }
+    for similarity in &dump.similar {
+        writeln!(
+            out,
+            r#"    <edge source="u:{}" target="u:{}">"#,
+            xml_escape(&similarity.user_name),
+            xml_escape(&similarity.similar_user_name)
+        )?;
+        write_data(&mut out, "label", Some("SIMILAR"))?;
+        write_data(&mut out, "score", similarity.score)?;
+        writeln!(out, "    </edge>")?;
+    }
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::{MemoryConnection, RegistrationEdge};
+    use crate::models::dataset::UserEventRow;
+    use crate::models::registration::RsvpState;
+    use crate::repo::memory::dataset::InMemoryDatasetRepository;
+    use chrono::Utc;
+
+    fn export(dump: &GraphDump, file: ExportFile) -> String {
+        let mut content = Vec::new();
+        write_export(dump, file, &mut content).unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
+    /// The rows of a CSV export, validated like the `import` command does.
+    fn rows<T>(csv: &str, parse: fn(&[&str]) -> Result<T, String>) -> Vec<T> {
+        csv::Reader::from_reader(csv.as_bytes())
+            .records()
+            .map(|record| parse(&record.unwrap().iter().collect::<Vec<_>>()).unwrap())
+            .collect()
+    }
+
+    fn user_event(user_name: &str, event_id: EventId, event_name: &str) -> UserEventRow {
+        UserEventRow::parse(&[user_name, &event_id.to_string(), event_name, "2099-01-01T10:00:00Z"]).unwrap()
+    }
+
+    fn registrations(dump: &GraphDump) -> Vec<(String, EventId, RsvpState)> {
+        let mut registrations: Vec<_> = dump
+            .registrations
+            .iter()
+            .map(|registration| (registration.user_name.clone(), registration.event_id, registration.state))
+            .collect();
+        registrations.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        registrations
+    }
+
+    #[rocket::async_test]
+    async fn csv_exports_import_back_to_the_same_graph() {
+        let source = InMemoryDatasetRepository::new(MemoryConnection::new().graph);
+        source
+            .import_user_events(&[
+                user_event("alice", 1, "Jazz & Blues"),
+                user_event("bob", 1, "Jazz & Blues"),
+                user_event("bob", 2, "Chess, \"open\""),
+            ])
+            .await
+            .unwrap();
+        source
+            .import_event_keywords(&[
+                EventKeywordRow { event_id: 1, keyword: "music".to_string() },
+                EventKeywordRow { event_id: 2, keyword: "games".to_string() },
+            ])
+            .await
+            .unwrap();
+        let dump = source.dump().await.unwrap();
+
+        let target = InMemoryDatasetRepository::new(MemoryConnection::new().graph);
+        target
+            .import_user_events(&rows(&export(&dump, ExportFile::UserEvents), UserEventRow::parse))
+            .await
+            .unwrap();
+        let unknown = target
+            .import_event_keywords(&rows(&export(&dump, ExportFile::EventKeywords), EventKeywordRow::parse))
+            .await
+            .unwrap();
+        assert!(unknown.is_empty());
+        let imported = target.dump().await.unwrap();
+
+        let names = |dump: &GraphDump| dump.users.iter().map(|user| user.name.clone()).collect::<Vec<_>>();
+        let events = |dump: &GraphDump| {
+            dump.events
+                .iter()
+                .map(|event| (event.id, event.name.clone(), event.start_datetime))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&imported), names(&dump));
+        assert_eq!(events(&imported), events(&dump));
+        assert_eq!(registrations(&imported), registrations(&dump));
+        assert_eq!(imported.keywords, dump.keywords);
+        assert_eq!(imported.event_keywords, dump.event_keywords);
+    }
+
+    #[rocket::async_test]
+    async fn user_events_hold_only_positive_registrations_to_active_events() {
+        let graph = MemoryConnection::new().graph;
+        let repo = InMemoryDatasetRepository::new(graph.clone());
+        repo.import_user_events(&[user_event("alice", 1, "Active"), user_event("alice", 2, "Archived")])
+            .await
+            .unwrap();
+        {
+            let mut data = graph.write();
+            data.events.get_mut(&2).unwrap().archived_at = Some(Utc::now().fixed_offset());
+            for (name, state) in [("bob", RsvpState::Attended), ("carol", RsvpState::Maybe), ("dave", RsvpState::Declined)] {
+                data.users.entry(name.to_string()).or_default();
+                data.registrations.insert((name.to_string(), 1), RegistrationEdge::new(state));
+            }
+        }
+        let dump = repo.dump().await.unwrap();
+        assert_eq!(dump.registrations.len(), 5);
+
+        let exported: Vec<(String, EventId)> = rows(&export(&dump, ExportFile::UserEvents), UserEventRow::parse)
+            .into_iter()
+            .map(|row| (row.user_name, row.event_id))
+            .collect();
+        assert_eq!(exported, [("alice".to_string(), 1), ("bob".to_string(), 1)]);
+
+        // The other formats have room for states and archived events
+        let lines = export(&dump, ExportFile::JsonLines);
+        let registered = lines
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|line| line["type"] == "registered_to")
+            .count();
+        assert_eq!(registered, 5);
+        assert_eq!(export(&dump, ExportFile::GraphMl).matches("<edge ").count(), 5);
+    }
+}
@@ -1,66 +1,400 @@
+use chrono::{Duration, Utc};
 use rocket::http::Status;
-use crate::models::event::{Event, EventUpdate};
+use rocket::serde::json::serde_json::{self, Value};
+use serde::Serialize;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+use crate::models::event::{
+    Event, EventChanges, EventId, EventListParams, EventPatch, EventSearchHit, EventUpdate, FeaturedUpdate, KeywordFilter,
+    SortOrder,
+    search_terms,
+};
+use crate::models::keyword::{KeywordSort, KeywordStats};
+use crate::models::revision::{self, Revision, RevisionAction};
+use crate::models::user::Role;
 use crate::repo::ApiError;
 use crate::repo::events::{EventRepository, EventRepoError};
-use crate::utils::api_response::ApiResponse;
+use crate::utils::api_response::{ApiResponse, PaginatedItemsResponse};
+use crate::utils::auth::AuthenticatedUser;
+use crate::utils::etag::IfMatch;
+use crate::utils::pagination::{PaginatedResponse, PaginationParams};
+
+/// How `GET /events/featured` picks and caches its events.
+#[derive(Debug, Clone)]
+pub struct FeaturedConfig {
+    /// Number of featured events returned.
+    pub size: u32,
+    /// Registrations made within this window count towards an event's velocity.
+    pub velocity_window: Duration,
+    /// How long a computed ranking is served before being recomputed.
+    pub cache_ttl: std::time::Duration,
+}
+
+impl Default for FeaturedConfig {
+    fn default() -> Self {
+        Self {
+            size: 3,
+            velocity_window: Duration::days(7),
+            cache_ttl: std::time::Duration::from_secs(60),
+        }
+    }
+}
 
 pub struct EventService {
-    event_repo: EventRepository
+    event_repo: Box<dyn EventRepository>,
+    featured_config: FeaturedConfig,
+    /// Last featured ranking and when it was computed.
+    featured_cache: Mutex<Option<(Instant, Vec<Event>)>>,
 }
 
 impl EventService {
     pub fn new(
-        event_repo: EventRepository,
+        event_repo: Box<dyn EventRepository>,
+        featured_config: FeaturedConfig,
     ) -> Self {
-        Self { event_repo }
+        Self {
+            event_repo,
+            featured_config,
+            featured_cache: Mutex::new(None),
+        }
     }
 
-    pub async fn get_event(&self, id: u16) -> ApiResponse<Event> {
+    /// Drops the cached featured events, so changes to events show up right away.
+    fn invalidate_featured(&self) {
+        *self.featured_cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub async fn get_event(&self, id: EventId) -> ApiResponse<Event> {
         match self.event_repo.find_by_id(id).await {
-            Ok(event) => ApiResponse::success(event, "Event found successfully"),
-            Err(EventRepoError::NotFound(_)) => ApiResponse::message_only(format!("No event found with ID: {}", id), Status::NotFound),
+            Ok(event) if event.archived_at.is_none() => ApiResponse::success(event, "Event found successfully"),
+            Ok(_) | Err(EventRepoError::NotFound(_)) => {
+                ApiResponse::message_only(format!("No event found with ID: {}", id), Status::NotFound)
+            }
             Err(e) => ApiResponse::message_only(format!("{}", e), Status::BadRequest),
         }
     }
 
-    pub async fn get_events(&self) -> ApiResponse<Vec<Event>> {
-        match self.event_repo.find_all().await {
-            Ok(events) => ApiResponse::success(events, "Events found successfully"),
+    pub async fn get_events(
+        &self,
+        params: EventListParams,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<Event> {
+        let filter = match params.to_filter() {
+            Ok(filter) => filter,
+            Err(msg) => return ApiResponse::message_only(msg, Status::BadRequest),
+        };
+
+        match self.event_repo.find_all(&filter, pagination.page, pagination.limit).await {
+            Ok((events, total)) => {
+                let paginated = PaginatedResponse::new(events, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Events found successfully")
+            }
             Err(e) => ApiResponse::message_only(format!("{}", e), Status::BadRequest)
         }
     }
 
-    pub async fn add_event(&self, event: EventUpdate) -> ApiResponse<Event> {
-        match self.event_repo.add(event).await {
-            Ok(event) => ApiResponse::success(event, "Events successfully created"),
+    /// Admins may manage any event. Otherwise `caller` has to organize it, as the
+    /// primary organizer or, when `allow_co_organizers` is set, as a co-organizer.
+    pub async fn check_organizer<T: Serialize>(
+        &self,
+        id: EventId,
+        caller: &AuthenticatedUser,
+        allow_co_organizers: bool,
+    ) -> Result<(), ApiResponse<T>> {
+        if caller.role == Role::Admin {
+            return Ok(());
+        }
+
+        let event = match self.event_repo.find_by_id(id).await {
+            Ok(event) => event,
+            Err(EventRepoError::NotFound(_)) => {
+                return Err(ApiResponse::message_only(format!("No event found with ID: {}", id), Status::NotFound))
+            }
+            Err(e) => return Err(ApiResponse::message_only(e.to_string(), e.status())),
+        };
+
+        let allowed = if allow_co_organizers {
+            event.is_organized_by(&caller.name)
+        } else {
+            event.organizer.as_deref() == Some(caller.name.as_str())
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(ApiResponse::message_only(
+                format!("User {} is not allowed to manage event {}", caller.name, id),
+                Status::Forbidden,
+            ))
+        }
+    }
+
+    /// The event unless it does not exist or is archived.
+    async fn find_active<T: Serialize>(&self, id: EventId) -> Result<Event, ApiResponse<T>> {
+        match self.event_repo.find_by_id(id).await {
+            Ok(event) if event.archived_at.is_none() => Ok(event),
+            Ok(_) | Err(EventRepoError::NotFound(_)) => {
+                Err(ApiResponse::message_only(format!("No event found with ID: {}", id), Status::NotFound))
+            }
+            Err(e) => Err(ApiResponse::message_only(e.to_string(), e.status())),
+        }
+    }
+
+    pub async fn add_event(&self, event: EventUpdate, organizer: &str) -> ApiResponse<Event> {
+        match self.event_repo.add(event, organizer).await {
+            Ok(event) => {
+                self.invalidate_featured();
+                ApiResponse::success(event, "Events successfully created")
+            }
             Err(e) => ApiResponse::message_only(format!("{}", e), Status::BadRequest)
         }
     }
 
-    pub async fn remove_event(&self, id: u16) -> ApiResponse<String> {
-        match self.event_repo.remove(id).await {
-            Ok(message) => ApiResponse::message_only(message, Status::Ok),
-            Err(e) => ApiResponse::message_only(format!("{}", e), Status::BadRequest),
+    pub async fn remove_event(&self, id: EventId, caller: &AuthenticatedUser) -> ApiResponse<String> {
+        if let Err(response) = self.check_organizer(id, caller, false).await {
+            return response;
+        }
+
+        match self.event_repo.remove(id, &caller.name).await {
+            Ok(message) => {
+                self.invalidate_featured();
+                ApiResponse::message_only(message, Status::Ok)
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn restore_event(&self, id: EventId, caller: &AuthenticatedUser) -> ApiResponse<Event> {
+        if let Err(response) = self.check_organizer(id, caller, false).await {
+            return response;
+        }
+
+        match self.event_repo.restore(id, &caller.name).await {
+            Ok(event) => {
+                self.invalidate_featured();
+                ApiResponse::success(event, "Event restored successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+
+    /// The event's revisions, newest first. Only its organizers and admins can see them.
+    pub async fn get_event_history(
+        &self,
+        id: EventId,
+        caller: &AuthenticatedUser,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<Revision> {
+        if let Err(e) = self.event_repo.find_by_id(id).await {
+            return ApiResponse::message_only(e.to_string(), e.status());
+        }
+        if let Err(response) = self.check_organizer(id, caller, true).await {
+            return response;
+        }
+
+        match self.event_repo.find_revisions(id, pagination.page, pagination.limit).await {
+            Ok((revisions, total)) => {
+                let paginated = PaginatedResponse::new(revisions, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Event history found successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+
+    /// Sets the event's fields back to what they were right after revision `number`,
+    /// recorded as a new revision.
+    pub async fn rollback_event(&self, id: EventId, number: i64, caller: &AuthenticatedUser) -> ApiResponse<Event> {
+        let current = match self.find_active(id).await {
+            Ok(event) => event,
+            Err(response) => return response,
+        };
+
+        let revisions = match self.event_repo.find_revisions_since(id, number).await {
+            Ok(revisions) => revisions,
+            Err(e) => return ApiResponse::message_only(e.to_string(), e.status()),
+        };
+        if revisions.first().map(|revision| revision.number) != Some(number) {
+            return ApiResponse::message_only(
+                format!("No revision {} found for event {}", number, id),
+                Status::NotFound,
+            );
+        }
+
+        let fields = revision::fields_at(&current, &revisions);
+        let changes = match serde_json::from_value::<EventPatch>(Value::Object(fields))
+            .map_err(|e| e.to_string())
+            .and_then(|patch| patch.changes(&current))
+        {
+            Ok(changes) => changes,
+            Err(msg) => return ApiResponse::message_only(msg, Status::BadRequest),
+        };
+
+        self.apply_changes(current, changes, &caller.name, RevisionAction::RolledBack).await
+    }
+
+    /// Archived events the caller may restore: all of them for admins, their own for others.
+    pub async fn get_archived_events(
+        &self,
+        caller: &AuthenticatedUser,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<Event> {
+        let organizer = (caller.role != Role::Admin).then_some(caller.name.as_str());
+
+        match self
+            .event_repo
+            .find_archived(organizer, pagination.page, pagination.limit)
+            .await
+        {
+            Ok((events, total)) => {
+                let paginated = PaginatedResponse::new(events, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Archived events found successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+
+    /// Hard-deletes events archived for longer than `retention`, returning how many.
+    pub async fn purge_archived_events(&self, retention: Duration) -> Result<u64, EventRepoError> {
+        let archived_before = (Utc::now() - retention).fixed_offset();
+        self.event_repo.purge_archived(archived_before).await
+    }
+
+    /// Replaces every field of the event, through the same path as [`EventService::patch_event`].
+    pub async fn edit_event(
+        &self,
+        id: EventId,
+        event: EventUpdate,
+        caller: &AuthenticatedUser,
+        if_match: &IfMatch,
+    ) -> ApiResponse<Event> {
+        self.patch_event(id, EventPatch::from(event), caller, if_match).await
+    }
+
+    /// Applies the fields present in `patch`. Edits that change nothing are not written.
+    /// Fails with 412 when `if_match` names another version of the event.
+    pub async fn patch_event(
+        &self,
+        id: EventId,
+        patch: EventPatch,
+        caller: &AuthenticatedUser,
+        if_match: &IfMatch,
+    ) -> ApiResponse<Event> {
+        if let Err(response) = self.check_organizer(id, caller, true).await {
+            return response;
+        }
+
+        let current = match self.find_active(id).await {
+            Ok(event) => event,
+            Err(response) => return response,
+        };
+        if !if_match.matches(&current.etag()) {
+            let e = EventRepoError::VersionMismatch(id);
+            return ApiResponse::message_only(e.to_string(), e.status());
         }
+
+        let changes = match patch.changes(&current) {
+            Ok(changes) => changes,
+            Err(msg) => return ApiResponse::message_only(msg, Status::BadRequest),
+        };
+
+        self.apply_changes(current, changes, &caller.name, RevisionAction::Edited).await
     }
 
-    pub async fn edit_event(&self, id: u16, event: EventUpdate) -> ApiResponse<Event> {
-        match self.event_repo.edit(id, event).await {
-            Ok(event) => ApiResponse::success(event, "Event edited successfully"),
+    /// Writes `changes` to the event if it is still at the version of `current` and
+    /// records them as a revision by `user`. Empty changes are not written.
+    async fn apply_changes(
+        &self,
+        current: Event,
+        changes: EventChanges,
+        user: &str,
+        action: RevisionAction,
+    ) -> ApiResponse<Event> {
+        if changes.is_empty() {
+            return ApiResponse::success(current, "Event unchanged");
+        }
+
+        match self
+            .event_repo
+            .edit(current.id, current.version, &changes, action, user)
+            .await
+        {
+            Ok(event) => {
+                self.invalidate_featured();
+                ApiResponse::success(event, "Event edited successfully")
+            }
             Err(e) => ApiResponse::message_only(e.to_string(), e.status())
         }
     }
 
     pub async fn get_featured_events(&self) -> ApiResponse<Vec<Event>> {
-        match self.event_repo.get_featured().await {
-            Ok(events) => ApiResponse::success(events, "Events found successfully"),
+        let cached = self
+            .featured_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|(computed_at, _)| computed_at.elapsed() < self.featured_config.cache_ttl)
+            .map(|(_, events)| events.clone());
+        if let Some(events) = cached {
+            return ApiResponse::success(events, "Events found successfully");
+        }
+
+        let registered_since = (Utc::now() - self.featured_config.velocity_window).fixed_offset();
+        match self.event_repo.get_featured(registered_since, self.featured_config.size).await {
+            Ok(events) => {
+                *self.featured_cache.lock().unwrap_or_else(PoisonError::into_inner) =
+                    Some((Instant::now(), events.clone()));
+                ApiResponse::success(events, "Events found successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+
+    /// Pins the event to the featured events, or unpins it when `update` is `None`.
+    pub async fn set_featured(&self, id: EventId, update: Option<FeaturedUpdate>) -> ApiResponse<Event> {
+        let until = match update.as_ref().map(FeaturedUpdate::until).transpose() {
+            Ok(until) => until,
+            Err(msg) => return ApiResponse::message_only(msg, Status::BadRequest),
+        };
+
+        match self.event_repo.set_featured_until(id, until).await {
+            Ok(event) => {
+                self.invalidate_featured();
+                let message = if until.is_some() {
+                    "Event pinned to featured events"
+                } else {
+                    "Event unpinned from featured events"
+                };
+                ApiResponse::success(event, message)
+            }
+            Err(EventRepoError::NotFound(_)) => ApiResponse::message_only(format!("No event found with ID: {}", id), Status::NotFound),
             Err(e) => ApiResponse::message_only(e.to_string(), e.status())
         }
     }
     
-    pub async fn get_events_by_keywords(&self, keyword: Vec<String>) -> ApiResponse<Vec<Event>> {
-        match self.event_repo.get_events_by_keywords(keyword).await {
-            Ok(events) => ApiResponse::success(events, "Events found successfully"),
+    pub async fn get_events_by_keywords(
+        &self,
+        keywords: KeywordFilter,
+        params: EventListParams,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<Event> {
+        let filter = match params.to_filter() {
+            Ok(filter) => filter,
+            Err(msg) => return ApiResponse::message_only(msg, Status::BadRequest),
+        };
+
+        match self
+            .event_repo
+            .get_events_by_keywords(&keywords, &filter, pagination.page, pagination.limit)
+            .await
+        {
+            Ok((events, total)) => {
+                let paginated = PaginatedResponse::new(events, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Events found successfully")
+            }
             Err(e) => ApiResponse::message_only(e.to_string(), e.status())
         }
     }
@@ -71,4 +405,115 @@ impl EventService {
             Err(e) => ApiResponse::message_only(e.to_string(), e.status())
         }
     }
+
+    pub async fn get_keyword_stats(
+        &self,
+        sort: KeywordSort,
+        order: Option<SortOrder>,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<KeywordStats> {
+        let order = order.unwrap_or(sort.default_order());
+
+        match self
+            .event_repo
+            .get_keyword_stats(sort, order, pagination.page, pagination.limit)
+            .await
+        {
+            Ok((stats, total)) => {
+                let paginated = PaginatedResponse::new(stats, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Keyword statistics found successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+
+    pub async fn search_events(
+        &self,
+        query: &str,
+        pagination: PaginationParams,
+    ) -> PaginatedItemsResponse<EventSearchHit> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return ApiResponse::message_only("Search query is required", Status::BadRequest);
+        }
+
+        match self.event_repo.search(&terms, pagination.page, pagination.limit).await {
+            Ok((hits, total)) => {
+                let paginated = PaginatedResponse::new(hits, total, &pagination);
+
+                ApiResponse::paginated(paginated, "Events found successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::{MemoryConnection, UserNode};
+    use crate::repo::memory::events::InMemoryEventRepository;
+
+    fn status<T>(response: &ApiResponse<T>) -> Status {
+        match response {
+            ApiResponse::Success { status, .. }
+            | ApiResponse::MessageOnly { status, .. }
+            | ApiResponse::Paginated { status, .. } => *status,
+        }
+    }
+
+    fn admin() -> AuthenticatedUser {
+        AuthenticatedUser { name: "admin".to_string(), role: Role::Admin }
+    }
+
+    /// The service and a repository over the same graph, with an `org` user.
+    fn service() -> (EventService, InMemoryEventRepository) {
+        let graph = MemoryConnection::new().graph;
+        graph.write().users.insert("org".to_string(), UserNode::default());
+        let service = EventService::new(Box::new(InMemoryEventRepository::new(graph.clone())), FeaturedConfig::default());
+        (service, InMemoryEventRepository::new(graph))
+    }
+
+    fn update(name: &str, capacity: Option<i64>) -> EventUpdate {
+        EventUpdate {
+            name: name.to_string(),
+            keywords: vec!["music".to_string()],
+            start_datetime: "2099-01-01T10:00:00Z".to_string(),
+            end_datetime: None,
+            timezone: None,
+            capacity,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn rollback_restores_the_fields_as_a_new_revision() {
+        let (service, repo) = service();
+        let event = repo.add(update("Concert", Some(10)), "org").await.unwrap();
+        let changes = EventPatch::from(update("Gig", None)).changes(&event).unwrap();
+        repo.edit(event.id, event.version, &changes, RevisionAction::Edited, "org").await.unwrap();
+
+        let ApiResponse::Success { data: rolled_back, .. } = service.rollback_event(event.id, 1, &admin()).await else {
+            panic!("rollback failed");
+        };
+        assert_eq!((rolled_back.name.as_str(), rolled_back.capacity), ("Concert", Some(10)));
+        assert_eq!(rolled_back.version, 3);
+
+        let (revisions, total) = repo.find_revisions(event.id, 1, 10).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!((revisions[0].action, revisions[0].user.as_str()), (RevisionAction::RolledBack, "admin"));
+        assert_eq!(revisions[0].changes.iter().map(|change| change.field.as_str()).collect::<Vec<_>>(), ["name", "capacity"]);
+    }
+
+    #[rocket::async_test]
+    async fn rollback_to_an_unknown_revision_is_not_found() {
+        let (service, repo) = service();
+        let event = repo.add(update("Concert", None), "org").await.unwrap();
+
+        for number in [0, 2] {
+            assert_eq!(status(&service.rollback_event(event.id, number, &admin()).await), Status::NotFound);
+        }
+        assert_eq!(status(&service.rollback_event(99, 1, &admin()).await), Status::NotFound);
+        assert_eq!(repo.find_revisions(event.id, 1, 10).await.unwrap().1, 1);
+    }
 }
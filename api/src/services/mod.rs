@@ -1,3 +1,4 @@
+pub mod dataset;
 pub mod events;
 pub mod users;
 pub mod users_events;
\ No newline at end of file
@@ -1,16 +1,19 @@
-use crate::models::user::User;
+use crate::models::user::{Credentials, PasswordUpdate, Role, User, UserUpdate};
 use crate::repo::ApiError;
-use crate::repo::users::UserRepository;
+use crate::repo::users::{UserRepoError, UserRepository};
 use crate::utils::api_response::{ApiResponse, PaginatedItemsResponse};
+use crate::utils::auth::{AuthToken, TokenKeys, hash_password, verify_password};
 use crate::utils::pagination::{PaginatedResponse, PaginationParams};
+use rocket::http::Status;
 
 pub struct UserService {
-    user_repo: UserRepository,
+    user_repo: Box<dyn UserRepository>,
+    token_keys: TokenKeys,
 }
 
 impl UserService {
-    pub fn new(user_repo: UserRepository) -> Self {
-        Self { user_repo }
+    pub fn new(user_repo: Box<dyn UserRepository>, token_keys: TokenKeys) -> Self {
+        Self { user_repo, token_keys }
     }
 
     pub async fn get_one(&self, user_name: &str) -> ApiResponse<User> {
@@ -22,10 +25,8 @@ impl UserService {
 
     pub async fn get_all(
         &self,
-        pagination: Option<PaginationParams>,
+        params: PaginationParams,
     ) -> PaginatedItemsResponse<User> {
-        let params = pagination.unwrap_or_default();
-
         match self.user_repo.find_all(params.page, params.limit).await {
             Ok((users, total)) => {
                 let paginated = PaginatedResponse::new(users, total, &params);
@@ -35,4 +36,130 @@ impl UserService {
             Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
         }
     }
+
+    pub async fn create_user(&self, user: UserUpdate) -> ApiResponse<User> {
+        if let Err(msg) = user.validate() {
+            return ApiResponse::message_only(msg, Status::BadRequest);
+        }
+
+        let Some(password) = &user.password else {
+            return ApiResponse::message_only("Password is required", Status::BadRequest);
+        };
+
+        let password_hash = match hash_password(password).await {
+            Ok(hash) => hash,
+            Err(e) => return ApiResponse::message_only(e, Status::InternalServerError),
+        };
+
+        match self.user_repo.create(&user.name, &password_hash).await {
+            Ok(user) => ApiResponse::created(user, "User successfully created"),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn update_user(&self, user_name: &str, user: UserUpdate) -> ApiResponse<User> {
+        if let Err(msg) = user.validate() {
+            return ApiResponse::message_only(msg, Status::BadRequest);
+        }
+
+        let password_hash = match &user.password {
+            Some(password) => match hash_password(password).await {
+                Ok(hash) => Some(hash),
+                Err(e) => return ApiResponse::message_only(e, Status::InternalServerError),
+            },
+            None => None,
+        };
+
+        match self
+            .user_repo
+            .update(user_name, &user.name, password_hash.as_deref())
+            .await
+        {
+            Ok(user) => ApiResponse::success(user, "User updated successfully"),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn delete_user(&self, user_name: &str) -> ApiResponse<String> {
+        match self.user_repo.delete(user_name).await {
+            Ok(message) => ApiResponse::message_only(message, Status::Ok),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn login(&self, credentials: Credentials) -> ApiResponse<AuthToken> {
+        let password_hash = match self.user_repo.find_password_hash(&credentials.name).await {
+            Ok(hash) => hash,
+            Err(UserRepoError::UserNotFound(_)) => None,
+            Err(e) => return ApiResponse::message_only(e.to_string(), e.status()),
+        };
+
+        // Unknown users and users without a password get the same answer as a wrong password
+        let valid = match password_hash {
+            Some(hash) => verify_password(&credentials.password, &hash).await,
+            None => false,
+        };
+        if !valid {
+            return ApiResponse::message_only("Invalid user name or password", Status::Unauthorized);
+        }
+
+        let token_version = match self.user_repo.find_token_version(&credentials.name).await {
+            Ok(version) => version,
+            Err(e) => return ApiResponse::message_only(e.to_string(), e.status()),
+        };
+
+        match self.token_keys.issue(&credentials.name, token_version) {
+            Ok(token) => ApiResponse::success(token, "Logged in successfully"),
+            Err(e) => ApiResponse::message_only(e, Status::InternalServerError),
+        }
+    }
+
+    /// Sets the user's password, which is how users imported without one get to log in.
+    /// Tokens issued with the old password stop working.
+    pub async fn set_password(&self, user_name: &str, password: PasswordUpdate) -> ApiResponse<User> {
+        if let Err(msg) = password.validate() {
+            return ApiResponse::message_only(msg, Status::BadRequest);
+        }
+
+        let password_hash = match hash_password(&password.password).await {
+            Ok(hash) => hash,
+            Err(e) => return ApiResponse::message_only(e, Status::InternalServerError),
+        };
+
+        match self.user_repo.update(user_name, user_name, Some(&password_hash)).await {
+            Ok(user) => ApiResponse::success(user, "Password updated successfully"),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    /// Returns the user a token was issued for, `None` when the user is gone or the token
+    /// predates their last password change.
+    pub async fn authenticate(&self, user_name: &str, token_version: i64) -> Option<User> {
+        let user = self.user_repo.find_one(user_name).await.ok()?;
+        let current = self.user_repo.find_token_version(user_name).await.ok()?;
+        (current == token_version).then_some(user)
+    }
+
+    pub async fn set_role(&self, user_name: &str, role: Role) -> ApiResponse<User> {
+        match self.user_repo.set_role(user_name, role).await {
+            Ok(user) => ApiResponse::success(user, "User role updated successfully"),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    /// Makes sure the bootstrap admin from the config exists. An existing user keeps
+    /// their password and is only promoted.
+    pub async fn ensure_admin(&self, user_name: &str, password: &str) -> Result<(), String> {
+        let password_hash = hash_password(password).await?;
+        match self.user_repo.create(user_name, &password_hash).await {
+            Ok(_) | Err(UserRepoError::UserAlreadyExists(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+
+        self.user_repo
+            .set_role(user_name, Role::Admin)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
 }
@@ -1,24 +1,29 @@
-use crate::models::event::Event;
+use crate::models::event::{Event, EventId};
+use crate::models::registration::{Attendee, AttendeeSort, Registration, RsvpState};
 use crate::repo::ApiError;
 use crate::repo::users_events::UserEventRepository;
 use crate::services::events::EventService;
 use crate::services::users::UserService;
-use crate::utils::api_response::ApiResponse;
-use crate::utils::api_response::ApiResponse::{MessageOnly, Success};
+use crate::utils::api_response::{ApiResponse, PaginatedItemsResponse};
+use crate::utils::api_response::ApiResponse::MessageOnly;
+use crate::utils::auth::AuthenticatedUser;
+use crate::utils::pagination::{PaginatedResponse, PaginationParams};
+use chrono::Utc;
 use rocket::http::Status;
 use std::sync::Arc;
 
 pub struct UserEventService {
     user_service: Arc<UserService>,
     event_service: Arc<EventService>,
-    user_event_repo: UserEventRepository,
+    user_event_repo: Box<dyn UserEventRepository>,
 }
 
+#[allow(clippy::single_match)]
 impl UserEventService {
     pub fn new(
         user_service: Arc<UserService>,
         event_service: Arc<EventService>,
-        user_event_repo: UserEventRepository,
+        user_event_repo: Box<dyn UserEventRepository>,
     ) -> Self {
         Self {
             user_service,
@@ -27,10 +32,18 @@ impl UserEventService {
         }
     }
 
+    /// Registers the user for the event with the given RSVP state. Going to an event
+    /// overlapping another one the user is going to is refused with 409, unless `force`
+    /// is set, in which case the user is registered and the response lists the conflicts.
+    /// When the event is full the user joins its waitlist if `join_waitlist` is set,
+    /// otherwise the registration is refused with 409.
     pub async fn assign_user_to_event(
         &self,
         user_name: &str,
-        event_id: u16,
+        event_id: EventId,
+        state: RsvpState,
+        force: bool,
+        join_waitlist: bool,
     ) -> ApiResponse<String> {
         match self.user_service.get_one(user_name).await {
             MessageOnly {
@@ -40,31 +53,89 @@ impl UserEventService {
             _ => {}
         };
 
-        match self.event_service.get_event(event_id).await {
-            MessageOnly {
-                message: m,
-                status: s,
-            } => return ApiResponse::message_only(m, s),
-            _ => {}
+        let event = match self.event_service.get_event(event_id).await {
+            ApiResponse::Success { data, .. } => data,
+            MessageOnly { message, status } => return ApiResponse::message_only(message, status),
+            _ => return ApiResponse::message_only("Unexpected response", Status::InternalServerError),
+        };
+
+        if state == RsvpState::Attended && event.start_datetime > Utc::now() {
+            return ApiResponse::message_only(
+                format!("Event {} has not started yet, attendance can't be recorded", event.id),
+                Status::BadRequest,
+            );
+        }
+
+        let conflicts = if state.is_positive() {
+            match self.find_conflicting_events(user_name, &event).await {
+                Ok(conflicts) => conflicts,
+                Err(response) => return response,
+            }
+        } else {
+            Vec::new()
         };
 
+        if !conflicts.is_empty() && !force {
+            return ApiResponse::message_only(
+                format!(
+                    "Event {} overlaps with events the user is registered to: {}",
+                    event.id,
+                    describe_events(&conflicts)
+                ),
+                Status::Conflict,
+            );
+        }
+
         match self
             .user_event_repo
-            .assign_user_to_event(user_name, event_id)
+            .assign_user_to_event(user_name, event_id, state, join_waitlist)
             .await
         {
-            Ok(_) => ApiResponse::message_only(
+            Ok(Registration::Full) => ApiResponse::message_only(
+                format!("Event {} is full", event_id),
+                Status::Conflict,
+            ),
+            Ok(Registration::Waitlisted { position }) => ApiResponse::message_only(
+                format!("Event is full, user has been added to the waitlist at position {}", position),
+                Status::Accepted,
+            ),
+            Ok(Registration::Registered) if conflicts.is_empty() => ApiResponse::message_only(
                 "User has been assigned to event".to_string(),
                 Status::Created,
             ),
+            Ok(Registration::Registered) => ApiResponse::message_only(
+                format!(
+                    "User has been assigned to event, but it overlaps with: {}",
+                    describe_events(&conflicts)
+                ),
+                Status::Created,
+            ),
             Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
         }
     }
 
+    /// Other events the user is going to that take place at the same time as `event`.
+    async fn find_conflicting_events(
+        &self,
+        user_name: &str,
+        event: &Event,
+    ) -> Result<Vec<Event>, ApiResponse<String>> {
+        let registered = self
+            .user_event_repo
+            .find_all_events_of_user(user_name, RsvpState::POSITIVE)
+            .await
+            .map_err(|e| ApiResponse::message_only(e.to_string(), e.status()))?;
+
+        Ok(registered
+            .into_iter()
+            .filter(|other| other.id != event.id && other.overlaps(event))
+            .collect())
+    }
+
     pub async fn unassign_user_from_event(
         &self,
         user_name: &str,
-        event_id: u16,
+        event_id: EventId,
     ) -> ApiResponse<String> {
         match self.user_service.get_one(user_name).await {
             MessageOnly {
@@ -87,15 +158,52 @@ impl UserEventService {
             .unassign_user_from_event(user_name, event_id)
             .await
         {
-            Ok(_) => ApiResponse::message_only(
+            Ok(None) => ApiResponse::message_only(
                 "User has been unassigned from event".to_string(),
                 Status::Ok,
             ),
+            Ok(Some(promoted)) => ApiResponse::message_only(
+                format!("User has been unassigned from event, {} has been moved off the waitlist", promoted),
+                Status::Ok,
+            ),
             Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
         }
     }
 
-    pub async fn find_all_events_of_user(&self, user_name: &str) -> ApiResponse<Vec<Event>> {
+    pub async fn find_attendees(
+        &self,
+        event_id: EventId,
+        states: &[RsvpState],
+        sort: AttendeeSort,
+        params: PaginationParams,
+    ) -> PaginatedItemsResponse<Attendee> {
+        match self.event_service.get_event(event_id).await {
+            MessageOnly {
+                message: m,
+                status: s,
+            } => return ApiResponse::message_only(m, s),
+            _ => {}
+        };
+
+        match self
+            .user_event_repo
+            .find_attendees(event_id, states, sort, params.page, params.limit)
+            .await
+        {
+            Ok((attendees, total)) => {
+                let paginated = PaginatedResponse::new(attendees, total, &params);
+
+                ApiResponse::paginated(paginated, "Attendees retrieved successfully")
+            }
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn find_all_events_of_user(
+        &self,
+        user_name: &str,
+        states: &[RsvpState],
+    ) -> ApiResponse<Vec<Event>> {
         match self.user_service.get_one(user_name).await {
             MessageOnly {
                 message: m,
@@ -106,7 +214,7 @@ impl UserEventService {
 
         match self
             .user_event_repo
-            .find_all_events_of_user(user_name)
+            .find_all_events_of_user(user_name, states)
             .await
         {
             Ok(events) => ApiResponse::success(events, "Events are ready".to_string()),
@@ -158,26 +266,108 @@ impl UserEventService {
         }
     }
 
-    pub async fn is_user_registered_to_event(
+    pub async fn get_registration_state(
         &self,
         user_name: &str,
-        event_id: u16,
-    ) -> ApiResponse<bool> {
+        event_id: EventId,
+    ) -> ApiResponse<Option<RsvpState>> {
         match self.user_service.get_one(user_name).await {
             MessageOnly {
                 message: m,
                 status: s,
             } => return ApiResponse::message_only(m, s),
             _ => {}
+        };
+
+        match self
+            .user_event_repo
+            .find_registration_state(user_name, event_id)
+            .await
+        {
+            Ok(Some(state)) => ApiResponse::success(Some(state), format!("User RSVP'd {} to event", state.as_str())),
+            Ok(None) => ApiResponse::success(None, "User is not registered to event"),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
         }
+    }
+
+    pub async fn find_events_organized_by_user(&self, user_name: &str) -> ApiResponse<Vec<Event>> {
+        match self.user_service.get_one(user_name).await {
+            MessageOnly {
+                message: m,
+                status: s,
+            } => return ApiResponse::message_only(m, s),
+            _ => {}
+        };
 
         match self
             .user_event_repo
-            .is_user_registered_to_event(user_name, event_id)
+            .find_events_organized_by_user(user_name)
             .await
         {
-            Ok(is_registered) => ApiResponse::success(is_registered, "User is registered to event"),
+            Ok(events) => ApiResponse::success(events, "Events are ready".to_string()),
             Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
         }
     }
+
+    pub async fn add_co_organizer(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+        caller: &AuthenticatedUser,
+    ) -> ApiResponse<String> {
+        if let Err(response) = self.event_service.check_organizer(event_id, caller, false).await {
+            return response;
+        }
+
+        match self.user_service.get_one(user_name).await {
+            MessageOnly {
+                message: m,
+                status: s,
+            } => return ApiResponse::message_only(m, s),
+            _ => {}
+        };
+
+        match self
+            .user_event_repo
+            .add_co_organizer(user_name, event_id)
+            .await
+        {
+            Ok(_) => ApiResponse::message_only(
+                "User has been added as co-organizer".to_string(),
+                Status::Created,
+            ),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+
+    pub async fn remove_co_organizer(
+        &self,
+        user_name: &str,
+        event_id: EventId,
+        caller: &AuthenticatedUser,
+    ) -> ApiResponse<String> {
+        if let Err(response) = self.event_service.check_organizer(event_id, caller, false).await {
+            return response;
+        }
+
+        match self
+            .user_event_repo
+            .remove_co_organizer(user_name, event_id)
+            .await
+        {
+            Ok(_) => ApiResponse::message_only(
+                "User has been removed from co-organizers".to_string(),
+                Status::Ok,
+            ),
+            Err(e) => ApiResponse::message_only(e.to_string(), e.status()),
+        }
+    }
+}
+
+fn describe_events(events: &[Event]) -> String {
+    events
+        .iter()
+        .map(|e| format!("{} ({})", e.name, e.id))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -31,6 +31,14 @@ impl<T: Serialize> ApiResponse<T> {
         }
     }
 
+    pub fn created(data: T, message: impl Into<String>) -> Self {
+        Self::Success {
+            data,
+            message: message.into(),
+            status: Status::Created
+        }
+    }
+
     pub fn message_only(message: impl Into<String>, status: Status) -> Self {
         Self::MessageOnly {
             message: message.into(),
@@ -0,0 +1,166 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::tokio::task::spawn_blocking;
+use rocket::Request;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::models::user::Role;
+use crate::services::users::UserService;
+use crate::utils::api_response::ApiResponse;
+
+/// Hashes on the blocking thread pool, Argon2 is too slow to run on the async executor.
+pub async fn hash_password(password: &str) -> Result<String, String> {
+    let password = password.to_string();
+    spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Verifies on the blocking thread pool, like [`hash_password`].
+pub async fn verify_password(password: &str, password_hash: &str) -> bool {
+    let (password, password_hash) = (password.to_string(), password_hash.to_string());
+    spawn_blocking(move || match PasswordHash::new(&password_hash) {
+        Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+        Err(_) => false,
+    })
+    .await
+    .unwrap_or(false)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    /// The user's token version when the token was issued.
+    ver: i64,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthToken {
+    pub token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// Signs and verifies the bearer tokens handed out by `POST /login`.
+#[derive(Clone)]
+pub struct TokenKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl_seconds: i64,
+}
+
+impl TokenKeys {
+    pub fn new(secret: &str, ttl_seconds: i64) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            ttl_seconds,
+        }
+    }
+
+    pub fn issue(&self, user_name: &str, token_version: i64) -> Result<AuthToken, String> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_name.to_string(),
+            ver: token_version,
+            iat: now,
+            exp: now + self.ttl_seconds,
+        };
+
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| e.to_string())?;
+
+        Ok(AuthToken {
+            token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.ttl_seconds,
+        })
+    }
+
+    /// Returns the user name and token version the token was issued for.
+    pub fn verify(&self, token: &str) -> Option<(String, i64)> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map(|data| (data.claims.sub, data.claims.ver))
+            .ok()
+    }
+}
+
+/// Request guard for routes that need a logged-in caller, read from the
+/// `Authorization: Bearer <token>` header. The role is looked up in the graph
+/// on every request, so role changes apply without logging in again.
+pub struct AuthenticatedUser {
+    pub name: String,
+    pub role: Role,
+}
+
+impl AuthenticatedUser {
+    /// Rejects the request with 403 unless the caller is `user_name`.
+    pub fn check_is<T: Serialize>(&self, user_name: &str) -> Result<(), ApiResponse<T>> {
+        if self.name == user_name {
+            Ok(())
+        } else {
+            Err(ApiResponse::message_only(
+                format!("User {} cannot act on behalf of {}", self.name, user_name),
+                Status::Forbidden,
+            ))
+        }
+    }
+
+    /// Rejects the request with 403 unless the caller has one of `roles`.
+    pub fn check_role<T: Serialize>(&self, roles: &[Role]) -> Result<(), ApiResponse<T>> {
+        if roles.contains(&self.role) {
+            Ok(())
+        } else {
+            let allowed: Vec<&str> = roles.iter().map(Role::as_str).collect();
+            Err(ApiResponse::message_only(
+                format!("This action requires one of the roles: {}", allowed.join(", ")),
+                Status::Forbidden,
+            ))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthenticatedUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let (Some(keys), Some(user_service)) = (
+            req.rocket().state::<TokenKeys>(),
+            req.rocket().state::<Arc<UserService>>(),
+        ) else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+
+        let token = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+
+        let Some((name, token_version)) = token.and_then(|token| keys.verify(token)) else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        // Tokens of deleted or renamed users, or issued before a password change, are no longer valid
+        match user_service.authenticate(&name, token_version).await {
+            Some(user) => Outcome::Success(AuthenticatedUser {
+                name: user.name,
+                role: user.role,
+            }),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
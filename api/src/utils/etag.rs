@@ -0,0 +1,54 @@
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Responder;
+use rocket::Request;
+use serde::Serialize;
+use crate::utils::api_response::ApiResponse;
+
+/// The `If-Match` request header. Absent or `*` matches any entity tag.
+pub struct IfMatch(Option<String>);
+
+impl IfMatch {
+    /// Strong comparison against each tag of the header's list.
+    pub fn matches(&self, etag: &str) -> bool {
+        match self.0.as_deref().map(str::trim) {
+            None | Some("*") => true,
+            Some(tags) => tags.split(',').any(|tag| tag.trim() == etag),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfMatch(req.headers().get_one("If-Match").map(str::to_string)))
+    }
+}
+
+/// An [`ApiResponse`] sent with an `ETag` header when it carries data.
+pub struct Tagged<T> {
+    response: ApiResponse<T>,
+    etag: Option<String>,
+}
+
+impl<T: Serialize> Tagged<T> {
+    pub fn new(response: ApiResponse<T>, etag: impl Fn(&T) -> String) -> Self {
+        let etag = match &response {
+            ApiResponse::Success { data, .. } => Some(etag(data)),
+            _ => None,
+        };
+        Self { response, etag }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Tagged<T> {
+    fn respond_to(self, req: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let mut response = self.response.respond_to(req)?;
+        if let Some(etag) = self.etag {
+            response.set_header(Header::new("ETag", etag));
+        }
+        Ok(response)
+    }
+}
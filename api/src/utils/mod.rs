@@ -1,3 +1,5 @@
 pub mod api_response;
+pub mod auth;
 pub mod error_catcher;
+pub mod etag;
 pub mod pagination;
\ No newline at end of file
@@ -26,6 +26,12 @@ pub struct PaginatedResponse<T> {
     pub pages: u32
 }
 
+/// Number of items before `page`. Computed in `i64`, and saturating, so large pages
+/// and limits don't overflow.
+pub fn skip(page: u32, limit: u32) -> i64 {
+    i64::from(page.saturating_sub(1)).saturating_mul(i64::from(limit))
+}
+
 impl<T> PaginatedResponse<T> {
     pub fn new(items: Vec<T>, total: u32, params: &PaginationParams) -> Self {
         let pages = (total as f64 / params.limit as f64).ceil() as u32;
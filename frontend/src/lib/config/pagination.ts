@@ -1,4 +1,8 @@
 export const USER_PAGINATION = {
     DEFAULT_PAGE: 1,
     DEFAULT_LIMIT: 21,
-}
\ No newline at end of file
+}
+export const EVENT_PAGINATION = {
+    DEFAULT_PAGE: 1,
+    DEFAULT_LIMIT: 21,
+}
@@ -1,7 +1,57 @@
-import {writable} from 'svelte/store';
+import {derived, get, writable} from 'svelte/store';
+import {browser} from '$app/environment';
+import type {Session} from '$lib/types/user';
+import type {EventCard} from '$lib/types/event';
 
-export const selectedUser = writable<string | null>(null);
+const SESSION_KEY = 'session';
 
-export function selectUser(userName: string) {
-    selectedUser.set(userName);
-}
\ No newline at end of file
+function loadSession(): Session | null {
+    if (!browser) return null;
+
+    const stored = localStorage.getItem(SESSION_KEY);
+    return stored ? JSON.parse(stored) : null;
+}
+
+export const session = writable<Session | null>(loadSession());
+
+session.subscribe((value) => {
+    if (!browser) return;
+
+    if (value) {
+        localStorage.setItem(SESSION_KEY, JSON.stringify(value));
+    } else {
+        localStorage.removeItem(SESSION_KEY);
+    }
+});
+
+// The API only lets users act on their own behalf, so the selected user is the logged in one
+export const selectedUser = derived(session, ($session) => $session?.name ?? null);
+
+export function logout() {
+    session.set(null);
+}
+
+export function authHeaders(): Record<string, string> {
+    const current = get(session);
+    return current ? {'Authorization': `Bearer ${current.token}`} : {};
+}
+
+// Mirror the API's checks, which still have the final say
+
+export function canCreateEvents(session: Session | null): boolean {
+    return session?.role === 'admin' || session?.role === 'organizer';
+}
+
+export function canEditEvent(session: Session | null, event: EventCard): boolean {
+    if (!session) return false;
+
+    return session.role === 'admin'
+        || event.organizer === session.name
+        || event.co_organizers.includes(session.name);
+}
+
+export function canDeleteEvent(session: Session | null, event: EventCard): boolean {
+    if (!session) return false;
+
+    return session.role === 'admin' || event.organizer === session.name;
+}
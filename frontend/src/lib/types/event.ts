@@ -5,4 +5,14 @@ export interface EventCard {
     date: Date;
     time: string;
     keywords: string[];
-}
\ No newline at end of file
+    attendee_count: number;
+    organizer: string | null;
+    co_organizers: string[];
+    version: number;
+}
+export interface KeywordStats {
+    name: string;
+    event_count: number;
+    upcoming_event_count: number;
+    registration_count: number;
+}
@@ -1,4 +1,12 @@
+export type Role = 'admin' | 'organizer' | 'attendee';
 
 export interface User {
     name: string
-}
\ No newline at end of file
+    role: Role
+}
+
+export interface Session {
+    name: string
+    role: Role
+    token: string
+}
@@ -1,12 +1,17 @@
 import type {PageServerLoad} from "./$types";
 import {getApiUrl} from "$lib/utils/api";
-import type {EventCard} from "$lib/types/event";
+import type {EventCard, KeywordStats} from "$lib/types/event";
+import type {ApiResponse, PaginatedResponse} from "$lib/types/pagination";
+import {EVENT_PAGINATION} from "$lib/config/pagination";
 
 export const load: PageServerLoad = async ({fetch, url}) => {
     try {
 				const keywordsParams = url.searchParams.getAll("keyword");
 				const searchParams = new URLSearchParams();
 				keywordsParams.forEach((keyword) => searchParams.append("keyword", keyword));
+				const page = Number(url.searchParams.get('page')) || EVENT_PAGINATION.DEFAULT_PAGE;
+				searchParams.set("page", String(page));
+				searchParams.set("limit", String(EVENT_PAGINATION.DEFAULT_LIMIT));
         let response = await fetch(getApiUrl("/events/filter?" + searchParams.toString()));
 
         if (!response.ok) {
@@ -14,20 +19,42 @@ export const load: PageServerLoad = async ({fetch, url}) => {
 
             throw new Error(data?.message);
         }
-        const events = await response.json();
+        const events = await response.json() as ApiResponse<PaginatedResponse<EventCard>>;
 
-				response = await fetch(getApiUrl("/events/keywords"));
-				if (!response.ok) {
-					const data = await response.json();
-					throw new Error(data?.message);
-				}
+				// Keywords with the most upcoming events first, paged through so none are left out
+				const keywords: string[] = [];
+				for (let keywordPage = 1, pages = 1; keywordPage <= pages; keywordPage++) {
+					response = await fetch(getApiUrl(`/events/keywords/stats?sort=upcoming&page=${keywordPage}&limit=100`));
+					if (!response.ok) {
+						const data = await response.json();
+						throw new Error(data?.message);
+					}
 
-				const keywords = ((await response.json()).data as string[])
+					const stats = ((await response.json()) as ApiResponse<PaginatedResponse<KeywordStats>>).data;
+					keywords.push(...stats.items.map((item) => item.name));
+					pages = stats.pages;
+				}
 
-        return {events: events.data as EventCard[], keywords: keywords, keywordsParams: keywordsParams};
+        return {
+            events: events.data.items,
+            pagination: {
+                total: events.data.total,
+                page: events.data.page,
+                limit: events.data.limit,
+                pages: events.data.pages
+            },
+            keywords: keywords,
+            keywordsParams: keywordsParams
+        };
     } catch (error: any) {
         return {
             events: [] as EventCard[],
+            pagination: {
+                total: 0,
+                page: 1,
+                limit: EVENT_PAGINATION.DEFAULT_LIMIT,
+                pages: 0
+            },
 						keywords: [],
 						keywordsParams: [],
             error: error.message
@@ -1,5 +1,6 @@
-// project graph in memory
+// project graph in memory, only "going" and "attended" registrations count
 MATCH (source:User)-[r:REGISTERED_TO]->(target:Event)
+WHERE coalesce(r.state, 'going') IN ['going', 'attended']
 RETURN gds.graph.project(
   'registrations',
   source,
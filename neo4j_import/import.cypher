@@ -1,4 +1,5 @@
-MATCH (n) DETACH DELETE n;
+// Migration nodes record the schema version applied by the API, see api/src/db/migrations.rs
+MATCH (n) WHERE NOT n:Migration DETACH DELETE n;
 
 CREATE CONSTRAINT user_name IF NOT EXISTS
 FOR (u:User)
@@ -12,6 +13,10 @@ CREATE CONSTRAINT eventKeyword_name IF NOT EXISTS
 FOR (k:EventKeyword)
 REQUIRE k.name IS UNIQUE;
 
+CREATE CONSTRAINT id_counter_name IF NOT EXISTS
+FOR (c:IdCounter)
+REQUIRE c.name IS UNIQUE;
+
 LOAD CSV WITH HEADERS FROM 'file:///user_events.csv' AS line
 CALL (line) {
 MERGE (u:User {name: line.user_name})
@@ -25,4 +30,10 @@ CALL (line) {
 MERGE (e:Event {id: toInteger(line.event_id)})
 MERGE (k:EventKeyword {name: line.keyword})
 MERGE (e)-[:HAS]->(k)
-} IN TRANSACTIONS OF 1000 ROWS;
\ No newline at end of file
+} IN TRANSACTIONS OF 1000 ROWS;
+
+// Move the event ID counter past the imported IDs
+MATCH (e:Event)
+WITH max(e.id) AS maxId
+MERGE (c:IdCounter {name: 'Event'})
+SET c.value = CASE WHEN c.value IS NULL OR c.value < maxId THEN maxId ELSE c.value END;